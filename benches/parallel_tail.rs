@@ -0,0 +1,53 @@
+//! Finds the channel counts and block sizes for which [`Tail::set_parallel`] is faster. Run with `cargo bench --bench parallel_tail --features parallel`.
+//!
+//! The mixing of the channels stays on one thread, so the parallel tail only gains on the delays and filters of each channel, and it hands the channels to rayon's global thread pool for every block. Set `RAYON_NUM_THREADS` to try other thread counts.
+//!
+//! Two threads on a single core, where there is nothing to gain, show the overhead: about 25 µs per block, which makes blocks of 64 frames 2-4x slower and is lost in the noise from blocks of 32768 frames. The gain on several cores has to be measured on the machine that renders.
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use knyst::{Sample, SampleRate};
+use knyst_reverb::Tail;
+
+const FRAMES: usize = 1 << 19;
+const SAMPLE_RATE: Sample = 48000.0;
+
+/// Time running a tail of `CHANNELS` channels over [`FRAMES`] frames in blocks of `block_size`
+fn run<const CHANNELS: usize>(block_size: usize, parallel: bool) -> Duration {
+    let mut tail = Tail::<CHANNELS>::new_seeded(4800 * 8, 0.9, 10.0, 1);
+    tail.init(block_size);
+    tail.set_parallel(parallel);
+    let damping = vec![8000.0; block_size];
+    let mut input: [Vec<Sample>; CHANNELS] = std::array::from_fn(|_| vec![0.0; block_size]);
+    let mut output: [Vec<Sample>; CHANNELS] = std::array::from_fn(|_| vec![0.0; block_size]);
+    // An impulse every block keeps the tail from decaying into denormals, which are slow
+    input[0][0] = 0.1;
+    let sample_rate = SampleRate::from(SAMPLE_RATE);
+    let start = Instant::now();
+    for _ in 0..FRAMES / block_size {
+        tail.process_block(black_box(&input), black_box(&mut output), &damping, sample_rate);
+    }
+    start.elapsed()
+}
+
+fn compare<const CHANNELS: usize>() {
+    for block_size in [64, 512, 4096, 32768] {
+        let serial = run::<CHANNELS>(block_size, false);
+        let parallel = run::<CHANNELS>(block_size, true);
+        println!(
+            "{CHANNELS:>3} channels, blocks of {block_size:>5}: serial {:>8.2} ms, parallel {:>8.2} ms, {:.2}x",
+            serial.as_secs_f64() * 1000.0,
+            parallel.as_secs_f64() * 1000.0,
+            serial.as_secs_f64() / parallel.as_secs_f64(),
+        );
+    }
+}
+
+fn main() {
+    let threads = rayon::current_num_threads();
+    println!("Running {FRAMES} frames on {threads} threads");
+    compare::<8>();
+    compare::<16>();
+    compare::<32>();
+    compare::<64>();
+}
@@ -0,0 +1,140 @@
+//! Compares the ways of mixing the channels of the tail. Run with `cargo bench --bench tail_matrix`.
+//!
+//! The first part shows the work saved by not regenerating the Hadamard matrix for every frame, and by using the fast transform which the tail uses instead of a cached matrix or the recursive transform it replaced. The second part runs a [`Tail`] with each [`MixMatrix`].
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use knyst::{Sample, SampleRate};
+use knyst_reverb::{MixMatrix, Tail};
+
+const CHANNELS: usize = 8;
+const FRAMES: usize = 1_000_000;
+const BLOCK_SIZE: usize = 64;
+const SAMPLE_RATE: Sample = 48000.0;
+
+fn hadamard() -> [[f64; CHANNELS]; CHANNELS] {
+    let mut matrix = [[0.0; CHANNELS]; CHANNELS];
+    matrix[0][0] = 1.0 / (CHANNELS as f64).sqrt();
+    let mut k = 1;
+    while k < CHANNELS {
+        for i in 0..k {
+            for j in 0..k {
+                matrix[i + k][j] = matrix[i][j];
+                matrix[i][j + k] = matrix[i][j];
+                matrix[i + k][j + k] = -matrix[i][j];
+            }
+        }
+        k += k;
+    }
+    matrix
+}
+
+fn multiply(matrix: &[[f64; CHANNELS]; CHANNELS], frame: &mut [f64; CHANNELS]) {
+    let input = *frame;
+    for (out, row) in frame.iter_mut().zip(matrix) {
+        *out = row.iter().zip(&input).map(|(m, s)| m * s).sum();
+    }
+}
+
+/// The recursive transform the diffusers and the tail used before the fast transform
+fn recursive_transform(frame: &mut [f64]) {
+    if frame.len() <= 1 {
+        return;
+    }
+    let d = frame.len() / 2;
+    recursive_transform(&mut frame[..d]);
+    recursive_transform(&mut frame[d..]);
+    for i in 0..d {
+        let a = frame[i];
+        let b = frame[i + d];
+        frame[i] = a + b;
+        frame[i + d] = a - b;
+    }
+}
+
+/// The same fast transform as the tail uses
+fn fast_transform(frame: &mut [f64; CHANNELS]) {
+    let mut half = 1;
+    while half < CHANNELS {
+        for start in (0..CHANNELS).step_by(half * 2) {
+            for i in start..start + half {
+                let a = frame[i];
+                let b = frame[i + half];
+                frame[i] = a + b;
+                frame[i + half] = a - b;
+            }
+        }
+        half *= 2;
+    }
+}
+
+fn time(name: &str, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    f();
+    let elapsed = start.elapsed();
+    println!("{name:<32} {:>8.2} ms", elapsed.as_secs_f64() * 1000.0);
+    elapsed
+}
+
+fn main() {
+    println!("Mixing {FRAMES} frames of {CHANNELS} channels");
+    let mut frame: [f64; CHANNELS] = std::array::from_fn(|i| i as f64 * 0.1);
+    let regenerated = time("hadamard, regenerated per frame", || {
+        for _ in 0..FRAMES {
+            multiply(black_box(&hadamard()), black_box(&mut frame));
+        }
+    });
+    let matrix = hadamard();
+    let cached = time("hadamard, cached matrix", || {
+        for _ in 0..FRAMES {
+            multiply(black_box(&matrix), black_box(&mut frame));
+        }
+    });
+    let normalisation = 1.0 / (CHANNELS as f64).sqrt();
+    let fast = time("hadamard, fast transform", || {
+        for _ in 0..FRAMES {
+            let frame = black_box(&mut frame);
+            fast_transform(frame);
+            frame.iter_mut().for_each(|s| *s *= normalisation);
+        }
+    });
+    let recursive = time("hadamard, recursive transform", || {
+        for _ in 0..FRAMES {
+            let frame = black_box(&mut frame);
+            recursive_transform(frame);
+            frame.iter_mut().for_each(|s| *s *= normalisation);
+        }
+    });
+    println!(
+        "caching saves {:.0}%, the fast transform another {:.0}%",
+        (1.0 - cached.as_secs_f64() / regenerated.as_secs_f64()) * 100.0,
+        (1.0 - fast.as_secs_f64() / cached.as_secs_f64()) * 100.0,
+    );
+    println!(
+        "the fast transform takes {:.0}% of the time of the recursive one",
+        fast.as_secs_f64() / recursive.as_secs_f64() * 100.0,
+    );
+
+    println!();
+    let blocks = FRAMES / BLOCK_SIZE;
+    let audio_seconds = (blocks * BLOCK_SIZE) as f64 / SAMPLE_RATE as f64;
+    println!("Running a Tail for {audio_seconds:.1} s of audio");
+    for matrix in [MixMatrix::Householder, MixMatrix::Hadamard] {
+        let mut tail = Tail::<CHANNELS>::new(4800, 0.9);
+        tail.init(BLOCK_SIZE);
+        tail.set_mix_matrix(matrix);
+        let damping = vec![8000.0; BLOCK_SIZE];
+        let mut input: [Vec<Sample>; CHANNELS] = std::array::from_fn(|_| vec![0.0; BLOCK_SIZE]);
+        let mut output: [Vec<Sample>; CHANNELS] = std::array::from_fn(|_| vec![0.0; BLOCK_SIZE]);
+        // An impulse every block keeps the tail from decaying into denormals, which are slow
+        input[0][0] = 0.1;
+        let sample_rate = SampleRate::from(SAMPLE_RATE);
+        let elapsed = time(&format!("tail, {matrix:?}"), || {
+            for _ in 0..blocks {
+                let output = black_box(&mut output);
+                tail.process_block(black_box(&input), output, &damping, sample_rate);
+            }
+        });
+        println!("{:>41.0}x real time", audio_seconds / elapsed.as_secs_f64());
+    }
+}
@@ -3,14 +3,13 @@ use knyst::{
     audio_backend::JackBackend,
     controller::print_error_handler,
     envelope::Envelope,
-    graph,
     handles::{graph_output, handle, Handle},
     modal_interface::knyst,
     prelude::*,
     sphere::{KnystSphere, SphereSettings},
     trig::interval_trig,
 };
-use knyst_reverb::{galactic::galactic, luff_verb};
+use knyst_reverb::galactic::galactic;
 use rand::{thread_rng, Rng};
 fn main() -> Result<()> {
     // let mut backend = CpalBackend::new(CpalBackendOptions::default())?;
@@ -54,6 +53,7 @@ fn main() -> Result<()> {
     // verb.input(sig * 0.125);
     let sig = verb;
     // let sig = verb * 0.25 + (sig * 0.25);
+    // Galactic has Galactic::OUTPUT_CHANNELS = 2 outputs, which go straight to the stereo output
     graph_output(0, sig);
 
     // std::thread::sleep(std::time::Duration::from_millis(150));
@@ -88,12 +88,12 @@ fn main() -> Result<()> {
         println!("1. Replace\n2.Brightness\n3.Detune\n4.Bigness\n5.Dry/wet\n");
         print!(": ");
         match std::io::stdin().read_line(&mut input) {
-            Ok(n) => {
+            Ok(_) => {
                 let trimmed_input = input.trim();
                 if let Ok(param) = trimmed_input.parse::<usize>() {
                     println!("param: {param}");
                     input.clear();
-                    if let Ok(n) = std::io::stdin().read_line(&mut input) {
+                    if let Ok(_n) = std::io::stdin().read_line(&mut input) {
                         let trimmed_input = input.trim();
                         dbg!(trimmed_input);
                         if let Ok(value) = trimmed_input.parse::<f32>() {
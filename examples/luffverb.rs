@@ -3,7 +3,6 @@ use knyst::{
     audio_backend::JackBackend,
     controller::print_error_handler,
     envelope::Envelope,
-    graph,
     handles::{graph_output, handle, Handle},
     modal_interface::knyst,
     prelude::*,
@@ -48,6 +47,7 @@ fn main() -> Result<()> {
     // verb.input(sig * 0.125);
     let sig = verb * 0.5;
     // let sig = verb * 0.25 + (sig * 0.25);
+    // LuffVerb has LuffVerb::OUTPUT_CHANNELS = 1 output, repeat it to both speakers
     graph_output(0, sig.repeat_outputs(1));
 
     // std::thread::sleep(std::time::Duration::from_millis(150));
@@ -84,7 +84,7 @@ fn main() -> Result<()> {
                 println!("{} bytes read", n);
                 println!("{}", input.trim());
                 let input = input.trim();
-                if let Ok(freq) = input.parse::<usize>() {
+                if let Ok(_freq) = input.parse::<usize>() {
                     // node0.freq(freq as f32);
                 } else if input == "q" {
                     break;
@@ -0,0 +1,63 @@
+//! Measurements of impulse responses for comparing the reverbs in this crate.
+
+use knyst::Sample;
+
+/// Fraction of the samples of Gaussian noise further than one standard deviation from 0, `erfc(1 / sqrt(2))`
+const GAUSSIAN_OUTSIDE_ONE_SIGMA: f64 = 0.317_310_507_862_914;
+
+/// The normalised echo density profile of an impulse response after Abel and Huang, "A Simple, Robust Measure of Reverberation Echo Density", AES Convention 121, 2006. For every sample it is the fraction of the samples in a `window` around it that are larger than the standard deviation of the window, divided by the fraction for Gaussian noise.
+///
+/// A few separate echoes give values close to 0 and a response which has become as dense as noise gives about 1, so the profile shows how quickly a reverb becomes diffuse. A window of 20 to 30 ms is common. The window is rectangular rather than the Hann window of the paper, and it is shifted inwards at the start and end of `ir` so that it is always full. Silent windows have a density of 0. The profile has the same length as `ir`.
+pub fn echo_density(ir: &[Sample], window: usize) -> Vec<f32> {
+    let window = window.clamp(1, ir.len().max(1));
+    // Sums of squares up to every sample, so that the standard deviation of any window takes two lookups
+    let mut square_sums = Vec::with_capacity(ir.len() + 1);
+    square_sums.push(0.0_f64);
+    for &s in ir {
+        square_sums.push(square_sums.last().unwrap() + (s as f64).powi(2));
+    }
+    (0..ir.len())
+        .map(|n| {
+            let start = n.saturating_sub(window / 2).min(ir.len() - window);
+            let end = start + window;
+            let mean_square = (square_sums[end] - square_sums[start]) / window as f64;
+            if mean_square <= 0.0 {
+                return 0.0;
+            }
+            let deviation = mean_square.sqrt();
+            let outside = ir[start..end].iter().filter(|s| (s.abs() as f64) > deviation).count();
+            (outside as f64 / window as f64 / GAUSSIAN_OUTSIDE_ONE_SIGMA) as f32
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use knyst::xorrng::XOrShift32Rng;
+
+    #[test]
+    fn echo_density_of_known_responses() {
+        let window = 1024;
+        // Gaussian noise from the sum of uniform noise
+        let mut rng = XOrShift32Rng::new(1);
+        let noise: Vec<Sample> = (0..48000)
+            .map(|_| (0..12).map(|_| rng.gen_f32() as Sample).sum::<Sample>() - 6.0)
+            .collect();
+        let density = echo_density(&noise, window);
+        assert_eq!(density.len(), noise.len());
+        assert!(density.iter().all(|d| (d - 1.0).abs() < 0.1));
+        // Equal echoes every 100 samples all stick out of the standard deviation
+        let echoes: Vec<Sample> = (0..48000).map(|i| if i % 100 == 0 { 1.0 } else { 0.0 }).collect();
+        let density = echo_density(&echoes, window);
+        let expected = 10.0 / 1024.0 / GAUSSIAN_OUTSIDE_ONE_SIGMA as f32;
+        assert!(density.iter().all(|d| (d - expected).abs() < 0.01));
+        // Sparse echoes turning into noise
+        let ir: Vec<Sample> = echoes[..24000].iter().chain(&noise[24000..]).copied().collect();
+        let density = echo_density(&ir, window);
+        assert!(density[10000] < 0.1);
+        assert!(density[40000] > 0.9);
+        assert!(echo_density(&[0.0; 100], window).iter().all(|&d| d == 0.0));
+        assert!(echo_density(&[], window).is_empty());
+    }
+}
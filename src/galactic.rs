@@ -35,11 +35,20 @@
 
 // Apply float dither
 
+// The airwindows naming and loop structure is kept so that the port can be compared to the original
+#![allow(non_snake_case, clippy::needless_range_loop, clippy::identity_op)]
+
+use std::f64::consts::PI;
+
 use knyst::gen::delay::StaticSampleDelay;
+use knyst::xorrng::XOrShift32Rng;
 use knyst::gen::GenState;
 use knyst::prelude::impl_gen;
 use knyst::{Sample, SampleRate};
 
+use crate::params::{ParamInfo, ParamUnit};
+use crate::reverb::{clear_delay, predelay, Allpass, ControlBuffers, Reverb};
+
 pub struct Galactic {
     delays_left: [StaticSampleDelay; 12],
     delays_right: [StaticSampleDelay; 12],
@@ -56,16 +65,222 @@ pub struct Galactic {
     iirAR: Sample,
     iirBL: Sample,
     iirBR: Sample,
+    shimmer_delay_left: StaticSampleDelay,
+    shimmer_delay_right: StaticSampleDelay,
+    /// Position of the shimmer read head within the pitch shifting window, 0-1
+    shimmer_phase: Sample,
+    /// Length of the pitch shifting window in samples
+    shimmer_window: Sample,
+    sample_rate: Sample,
+    /// Smoothed value of the mix parameter, NaN until the first sample
+    mix_smoothed: Sample,
+    /// Gain of the wet signal before the mix, set by the wet_gain_db parameter
+    wet_gain: DbGain,
+    /// Gain of the mixed output, set by the gain_db parameter
+    gain: DbGain,
+    /// Smoothed value of the replace parameter, which sets the feedback and the input level, NaN until the first sample
+    replace_smoothed: Sample,
+    /// The size the delays are crossfading from and the one they are crossfading to, both scaled to 0.1-1 and the same when no crossfade is running. NaN until the first sample.
+    size_from: Sample,
+    size_to: Sample,
+    /// Progress of the crossfade between the sizes, 1 when it is done
+    size_fade: Sample,
+    /// Delays of the input going into the reverb, the length of their buffers and their smoothed delay times in samples, NaN until the first sample
+    predelay_left: StaticSampleDelay,
+    predelay_right: StaticSampleDelay,
+    predelay_length: usize,
+    predelay_smoothed: [Sample; 2],
+    /// Allpasses after the predelay of each channel, used if `predelay_diffuse` is set
+    predelay_diffuse: bool,
+    predelay_diffusers: [[Allpass; 2]; 2],
+    /// How much the cross feedback is made symmetric, 0-1, see [`Galactic::with_mono_compat`]
+    mono_compat: Sample,
+    /// How the inputs are routed into the reverb
+    input_matrix: InputMatrix,
+    /// Levels of the input and of the reverb followed by the darkening, and how long the reverb has been ringing on its own, 0-1
+    darken_levels: [Sample; 2],
+    darken_age: Sample,
+    /// Wow and flutter replacing part of the detune modulation
+    tape_mod: TapeMod,
+    /// State for the [`Reverb`] interface
+    reverb_mix: Sample,
+    reverb_size: Sample,
+    reverb_controls: ControlBuffers,
+}
+
+impl Galactic {
+    /// Number of output channels of the node, left and right. The handle can be connected to a stereo output directly.
+    pub const OUTPUT_CHANNELS: usize = 2;
+    /// Name, range, default and unit of every control input, in the order of the inputs. The defaults are those of the original plugin. The audio inputs are not included.
+    ///
+    /// Inputs that aren't connected read 0, which is a valid setting of every parameter but rarely a useful patch: a `replace` of 0 freezes the reverb without letting any input in and a `mix` of 0 is fully dry.
+    ///
+    /// The feedback of `shimmer_mix` is scaled by the room the feedback of the network leaves, so the shimmer is strongest at a `replace` of 1 and fades out towards a `replace` of 0, where a frozen tail has no room for more feedback. Even at its strongest the shimmer is a subtle layer. A `wow_depth` and `flutter_depth` of 0 turn the [`TapeMod`] off, and a `gain_db` and `wet_gain_db` of 0 leave the levels as they are.
+    pub fn param_metadata() -> &'static [ParamInfo] {
+        const PARAMS: [ParamInfo; 16] = [
+            ParamInfo::new("size", 0.0, 1.0, 1.0, ParamUnit::Ratio),
+            ParamInfo::new("replace", 0.0, 1.0, 0.5, ParamUnit::Ratio),
+            ParamInfo::new("brightness", 0.0, 1.0, 0.5, ParamUnit::Ratio),
+            ParamInfo::new("damping", 0.0, 1.0, 0.0, ParamUnit::Ratio),
+            ParamInfo::new("detune", 0.0, 1.0, 0.5, ParamUnit::Ratio),
+            ParamInfo::new("mix", 0.0, 1.0, 1.0, ParamUnit::Ratio),
+            ParamInfo::new("shimmer_mix", 0.0, 1.0, 0.0, ParamUnit::Ratio),
+            ParamInfo::new("shimmer_semitones", -24.0, 24.0, 12.0, ParamUnit::Semitones),
+            ParamInfo::new("predelay_left_ms", 0.0, MAX_PREDELAY_MS, 0.0, ParamUnit::Milliseconds),
+            ParamInfo::new("predelay_right_ms", 0.0, MAX_PREDELAY_MS, 0.0, ParamUnit::Milliseconds),
+            ParamInfo::new("detune_rate", 0.0, MAX_DETUNE_RATE, 0.0, ParamUnit::Hz),
+            ParamInfo::new("darken", 0.0, 1.0, 0.0, ParamUnit::Ratio),
+            ParamInfo::new("wow_depth", 0.0, 1.0, 0.0, ParamUnit::Ratio),
+            ParamInfo::new("flutter_depth", 0.0, 1.0, 0.0, ParamUnit::Ratio),
+            ParamInfo::new("gain_db", -MAX_GAIN_DB, MAX_GAIN_DB, 0.0, ParamUnit::Decibels),
+            ParamInfo::new("wet_gain_db", -MAX_GAIN_DB, MAX_GAIN_DB, 0.0, ParamUnit::Decibels),
+        ];
+        &PARAMS
+    }
+    /// Clear the delays and filters so that the reverb is silent. Real time safe.
+    ///
+    /// The dither seeds `fpdL` and `fpdR` and the detune modulation keep running rather than starting over, so that every reset doesn't begin the same dither pattern. Without detune only the dither and the faint noise which replaces a silent input differ from one reset to the next, both far below -120 dB, with detune the vibrato also carries on from where it was.
+    pub fn reset(&mut self) {
+        for delays in [&mut self.delays_left, &mut self.delays_right] {
+            for (delay, time) in delays.iter_mut().zip(GALACTIC_DELAY_TIMES) {
+                clear_delay(delay, galactic_delay_length(time, self.sample_rate));
+            }
+        }
+        clear_delay(&mut self.detune_delay_left, 256);
+        clear_delay(&mut self.detune_delay_right, 256);
+        let shimmer_length = self.shimmer_window as usize + 2;
+        clear_delay(&mut self.shimmer_delay_left, shimmer_length);
+        clear_delay(&mut self.shimmer_delay_right, shimmer_length);
+        self.feedback = [[0.0; 4]; 2];
+        self.lowpass_pre = [0., 0.];
+        self.lowpass_post = [0., 0.];
+        self.iirAL = 0.;
+        self.iirAR = 0.;
+        self.iirBL = 0.;
+        self.iirBR = 0.;
+        self.shimmer_phase = 0.;
+        self.mix_smoothed = Sample::NAN;
+        self.wet_gain.reset();
+        self.gain.reset();
+        self.replace_smoothed = Sample::NAN;
+        self.size_to = Sample::NAN;
+        self.darken_levels = [0.0; 2];
+        self.darken_age = 0.0;
+        clear_delay(&mut self.predelay_left, self.predelay_length);
+        clear_delay(&mut self.predelay_right, self.predelay_length);
+        self.predelay_smoothed = [Sample::NAN; 2];
+        for allpass in self.predelay_diffusers.iter_mut().flatten() {
+            allpass.clear();
+        }
+    }
+    /// Smear the predelay through two allpasses instead of delaying the input as a single slap. Off by default.
+    ///
+    /// The predelay is split between a plain delay and the two allpasses, so the first echo arrives after a fifth of the predelay and the echoes thicken up to the full predelay and beyond, into the reverb. The diffusion follows the `predelay_left_ms` and `predelay_right_ms` inputs and at a predelay of 0 the allpasses are a single sample long, which leaves the spectrum as it is.
+    pub fn with_predelay_diffuse(mut self, predelay_diffuse: bool) -> Self {
+        self.predelay_diffuse = predelay_diffuse;
+        self
+    }
+    /// Make the feedback between the channels more symmetric so that the output folds down to mono more cleanly, from 0, the full cross feedback of the original plugin and the default, to 1.
+    ///
+    /// Each channel of the reverb is normally fed back into the other one. The option blends in `amount / 2` of each channel's own feedback instead, so at 1 both channels get the same feedback and after one pass through the network the tail is the same in both, only the first echoes differ. This trades width for mono compatibility: the more symmetric the feedback, the more correlated and the narrower the tail. The blend never raises the loop gain: the sum of the channels decays as before and their difference decays faster.
+    pub fn with_mono_compat(mut self, amount: Sample) -> Self {
+        self.mono_compat = amount.clamp(0.0, 1.0);
+        self
+    }
+    /// Route the left and right inputs into the reverb through an [`InputMatrix`], by default they go straight through. The dry signal is always the unchanged input.
+    pub fn with_input_matrix(mut self, input_matrix: InputMatrix) -> Self {
+        self.input_matrix = input_matrix;
+        self
+    }
+}
+
+/// How the inputs of [`Galactic`] feed the reverb, see [`Galactic::with_input_matrix`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputMatrix {
+    /// Left into left and right into right, as in the original plugin
+    #[default]
+    Straight,
+    /// Left into right and right into left, which mirrors the reverb
+    Swap,
+    /// Half the sum of the channels into both, so the reverb doesn't follow the panning of the input
+    Sum,
+    /// Half the difference of the channels, the side signal, into the left and its negation into the right. Only what differs between the channels is reverberated and a centred sound stays dry.
+    Difference,
+}
+impl InputMatrix {
+    #[inline]
+    fn apply(self, left: Sample, right: Sample) -> (Sample, Sample) {
+        match self {
+            InputMatrix::Straight => (left, right),
+            InputMatrix::Swap => (right, left),
+            InputMatrix::Sum => {
+                let mid = (left + right) * 0.5;
+                (mid, mid)
+            }
+            InputMatrix::Difference => {
+                let side = (left - right) * 0.5;
+                (side, -side)
+            }
+        }
+    }
+}
+
+impl Default for Galactic {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 const GALACTIC_DELAY_TIMES: [usize; 12] = [
     6480, 3660, 1720, 680, 9700, 6000, 2320, 940, 15220, 8460, 4540, 3200,
 ];
+/// Full length in samples of a delay of the network which is `time` samples long at 44.1 kHz, at least one sample
+fn galactic_delay_length(time: usize, sample_rate: Sample) -> usize {
+    (((time as Sample / 44100.) * sample_rate) as usize).max(1)
+}
+/// Gain of the shimmer fed back into the network at a `shimmer_mix` of 1, relative to the headroom of `1 - 8 * regen` that the feedback of the network leaves. The headroom is 0.5 at a `replace` of 1 and falls to 0 at a `replace` of 0, so the shimmer feeds back at most 0.2 and fades out as the tail freezes: a frozen tail keeps its level by itself and any more feedback would make it grow without bound.
+const SHIMMER_GAIN: Sample = 0.4;
+/// Length of the crossfading window of the shimmer pitch shifter
+const SHIMMER_WINDOW_SECONDS: Sample = 0.05;
+/// Time constant of the smoothing applied to the replace parameter so that steps in it don't jump the feedback and the input level
+const REPLACE_SMOOTHING_SECONDS: Sample = 0.02;
+/// Length of the crossfade of every delay from its old to its new length when the size changes. A size that keeps changing is followed one crossfade at a time, so it lags by up to two crossfades.
+const SIZE_CROSSFADE_SECONDS: Sample = 0.05;
+/// Time constant of the smoothing applied to the mix parameter so that steps in it don't click
+const MIX_SMOOTHING_SECONDS: Sample = 0.005;
+/// Distance from the target at which the smoothed mix jumps to it
+const MIX_SNAP: Sample = 1e-4;
+/// Time constant of the smoothing applied to the output gain so that steps in it don't click
+const GAIN_SMOOTHING_SECONDS: Sample = 0.005;
+/// Largest boost and cut of the gain_db and wet_gain_db inputs
+pub const MAX_GAIN_DB: Sample = 24.0;
+/// Slowest speed of the detune modulation in Hz when set by the detune_rate input
+pub const MIN_DETUNE_RATE: Sample = 0.01;
+/// Fastest speed of the detune modulation in Hz when set by the detune_rate input
+pub const MAX_DETUNE_RATE: Sample = 10.0;
+/// Time constant of the level followers of the darkening
+const DARKEN_LEVEL_SECONDS: Sample = 0.05;
+/// The tail counts as ringing on its own when the input is this much quieter than it
+const DARKEN_INPUT_RATIO: Sample = 0.1;
+/// Time for the darkening to reach its full depth once the tail rings on its own. It goes back 10 times faster when new input arrives.
+const DARKEN_SECONDS: Sample = 3.0;
+/// How far a darken of 1 lowers the coefficient of the output lowpass
+const DARKEN_DEPTH: Sample = 0.9;
+/// Longest predelay of each channel
+pub const MAX_PREDELAY_MS: Sample = 250.0;
+/// Time constant of the smoothing of the predelay times. A change of the predelay glides, bending the pitch of the input briefly instead of clicking.
+const PREDELAY_SMOOTHING_SECONDS: Sample = 0.05;
+/// Part of the predelay taken by the plain delay and by each allpass when the predelay is diffused, adding up to 1
+const PREDELAY_PLAIN_FRACTION: Sample = 0.2;
+const PREDELAY_DIFFUSER_FRACTIONS: [Sample; 2] = [0.3, 0.5];
+/// Gain of the allpasses diffusing the predelay
+const PREDELAY_DIFFUSER_GAIN: Sample = 0.5;
 
 #[impl_gen]
 impl Galactic {
+    /// The dither and the [`TapeMod`] are seeded from knyst's randomness source, see [`knyst::gen::random::next_randomness_seed`], so creating the nodes of a program in the same order gives the same output on every run
     pub fn new() -> Self {
-        let mut rng = fastrand::Rng::with_seed(knyst::gen::random::next_randomness_seed());
+        let mut rng = crate::luffverb::seeded_rng(knyst::gen::random::next_randomness_seed());
         Self {
             delays_left: std::array::from_fn(|_| StaticSampleDelay::new(1)),
             delays_right: std::array::from_fn(|_| StaticSampleDelay::new(1)),
@@ -73,8 +288,8 @@ impl Galactic {
             detune_delay_right: StaticSampleDelay::new(1),
             lowpass_pre: [0., 0.],
             lowpass_post: [0., 0.],
-            fpdL: rng.u32(16386..std::u32::MAX),
-            fpdR: rng.u32(16386..std::u32::MAX),
+            fpdL: 16386 + rng.gen_u32() % (u32::MAX - 16386),
+            fpdR: 16386 + rng.gen_u32() % (u32::MAX - 16386),
             vibM: 3.,
             feedback: [[0.0; 4]; 2],
             oldfpd: 429496.7295,
@@ -82,16 +297,41 @@ impl Galactic {
             iirAR: 0.,
             iirBL: 0.,
             iirBR: 0.,
+            shimmer_delay_left: StaticSampleDelay::new(1),
+            shimmer_delay_right: StaticSampleDelay::new(1),
+            shimmer_phase: 0.,
+            shimmer_window: 1.,
+            sample_rate: 44100.,
+            mix_smoothed: Sample::NAN,
+            wet_gain: DbGain::new(),
+            gain: DbGain::new(),
+            replace_smoothed: Sample::NAN,
+            size_from: Sample::NAN,
+            size_to: Sample::NAN,
+            size_fade: 1.0,
+            predelay_left: StaticSampleDelay::new(1),
+            predelay_right: StaticSampleDelay::new(1),
+            predelay_length: 1,
+            predelay_smoothed: [Sample::NAN; 2],
+            predelay_diffuse: false,
+            mono_compat: 0.0,
+            input_matrix: InputMatrix::Straight,
+            predelay_diffusers: std::array::from_fn(|_| std::array::from_fn(|_| Allpass::new(1))),
+            darken_levels: [0.0; 2],
+            darken_age: 0.0,
+            tape_mod: TapeMod::new(knyst::gen::random::next_randomness_seed()),
+            reverb_mix: 1.0,
+            reverb_size: 1.0,
+            reverb_controls: ControlBuffers::default(),
         }
     }
     pub fn init(&mut self, sample_rate: SampleRate) {
+        self.sample_rate = *sample_rate;
         for (delay, time) in self.delays_left.iter_mut().zip(GALACTIC_DELAY_TIMES) {
-            let time = (time as Sample / 44100.) * *sample_rate;
-            *delay = StaticSampleDelay::new(time as usize);
+            *delay = StaticSampleDelay::new(galactic_delay_length(time, *sample_rate));
         }
         for (delay, time) in self.delays_right.iter_mut().zip(GALACTIC_DELAY_TIMES) {
-            let time = (time as Sample/ 44100.) * *sample_rate;
-            *delay = StaticSampleDelay::new(time as usize);
+            *delay = StaticSampleDelay::new(galactic_delay_length(time, *sample_rate));
         }
         // self.detune_delay_left =
         //     StaticSampleDelay::new((0.07054421768707483 * *sample_rate) as usize);
@@ -101,9 +341,26 @@ impl Galactic {
             StaticSampleDelay::new(256);
         self.detune_delay_right =
             StaticSampleDelay::new(256);
+        let shimmer_window = (SHIMMER_WINDOW_SECONDS * *sample_rate) as usize;
+        self.shimmer_delay_left = StaticSampleDelay::new(shimmer_window + 2);
+        self.shimmer_delay_right = StaticSampleDelay::new(shimmer_window + 2);
+        self.shimmer_window = shimmer_window as Sample;
+        self.shimmer_phase = 0.;
+        // One extra sample so that the longest predelay can be read after the input is written
+        self.predelay_length = (MAX_PREDELAY_MS * 0.001 * *sample_rate) as usize + 2;
+        self.predelay_left = StaticSampleDelay::new(self.predelay_length);
+        self.predelay_right = StaticSampleDelay::new(self.predelay_length);
+        self.predelay_smoothed = [Sample::NAN; 2];
+        let predelay_max = (self.predelay_length - 2) as Sample;
+        for allpasses in &mut self.predelay_diffusers {
+            for (allpass, fraction) in allpasses.iter_mut().zip(PREDELAY_DIFFUSER_FRACTIONS) {
+                *allpass = Allpass::new((predelay_max * fraction) as usize + 2);
+            }
+        }
         self.lowpass_pre = [0., 0.];
         self.lowpass_post = [0., 0.];
     }
+    #[allow(clippy::too_many_arguments)]
     pub fn process(
         &mut self,
         left: &[Sample],
@@ -111,12 +368,38 @@ impl Galactic {
         size: &[Sample],
         replace: &[Sample],
         brightness: &[Sample],
+        damping: &[Sample],
         detune: &[Sample],
         mix: &[Sample],
+        shimmer_mix: &[Sample],
+        shimmer_semitones: &[Sample],
+        predelay_left_ms: &[Sample],
+        predelay_right_ms: &[Sample],
+        detune_rate: &[Sample],
+        darken: &[Sample],
+        wow_depth: &[Sample],
+        flutter_depth: &[Sample],
+        gain_db: &[Sample],
+        wet_gain_db: &[Sample],
         left_out: &mut [Sample],
         right_out: &mut [Sample],
         sample_rate: SampleRate,
     ) -> GenState {
+        debug_assert_eq!(
+            left.len(),
+            right.len(),
+            "Galactic needs left and right inputs of the same length"
+        );
+        debug_assert_eq!(
+            left_out.len(),
+            right_out.len(),
+            "Galactic needs left and right outputs of the same length"
+        );
+        debug_assert_eq!(
+            left.len(),
+            left_out.len(),
+            "Galactic needs inputs and outputs of the same length"
+        );
         let mut overallscale = 1.0;
         overallscale /= 44100.0;
         overallscale *= *sample_rate;
@@ -124,20 +407,51 @@ impl Galactic {
 	// double regen = 0.0625+((1.0-A)*0.0625); // High (0.125) if Replace is low
 	// double attenuate = (1.0 - (regen / 0.125))*1.333; // 1.33 if regen is low / replace is high  
 
-        let regen = 0.0625 + ((1.0 - replace[0]) * 0.0625);
-        let attenuate = (1.0 - (regen / 0.125)) * 1.333; // 1.33 if regen is high / replace is low
-        let lowpass = (1.00001 - (1.0 - brightness[0])).powi(2) / (overallscale).sqrt(); // (0.00001 + Brightness).powi(2)/overallscale.sqrt()
-        let drift = detune[0].powi(3) * 0.001; // Detune.powi(3) * 0.001
-        let size = (size[0] * 0.9) + 0.1;
-        let wet = 1.0 - (1.0 - mix[0]).powi(3);
+        // Every parameter is read per sample. Sizes outside of 0-1 are clamped and NaN is the smallest size.
+        let size_target = |size: Sample| {
+            let size = if size.is_nan() { 0.0 } else { size.clamp(0.0, 1.0) };
+            (size * 0.9) + 0.1
+        };
+        if self.size_to.is_nan() {
+            self.size_to = size_target(size[0]);
+            self.size_fade = 1.0;
+        }
+        let size_fade_step = 1.0 / (SIZE_CROSSFADE_SECONDS * *sample_rate);
+        // Mix is smoothed per sample so that it doesn't step when it is only set once per block
+        let mix_coeff = 1.0 - (-1.0 / (MIX_SMOOTHING_SECONDS * *sample_rate)).exp();
+        if self.mix_smoothed.is_nan() {
+            self.mix_smoothed = mix[0].clamp(0.0, 1.0);
+        }
+        // The output gain is smoothed like the mix, and in linear terms so that it glides evenly
+        let gain_coeff = 1.0 - (-1.0 / (GAIN_SMOOTHING_SECONDS * *sample_rate)).exp();
+        // Replace is smoothed per sample as well, a constant replace stays exactly at its value
+        let replace_coeff = 1.0 - (-1.0 / (REPLACE_SMOOTHING_SECONDS * *sample_rate)).exp();
+        let predelay_coeff = 1.0 - (-1.0 / (PREDELAY_SMOOTHING_SECONDS * *sample_rate)).exp();
+        let darken_level_coeff = 1.0 - (-1.0 / (DARKEN_LEVEL_SECONDS * *sample_rate)).exp();
+        let darken_age_step = 1.0 / (DARKEN_SECONDS * *sample_rate);
+        let predelay_max = (self.predelay_length - 2) as Sample;
+        let predelay_samples = |ms: Sample| (ms * 0.001 * *sample_rate).clamp(0.0, predelay_max);
+        let predelay_ms = [predelay_left_ms, predelay_right_ms];
+        for (smoothed, ms) in self.predelay_smoothed.iter_mut().zip(predelay_ms) {
+            if smoothed.is_nan() {
+                *smoothed = predelay_samples(ms[0]);
+            }
+        }
+        // The pitch shift ratio is only recalculated when shimmer_semitones changes
+        let mut shimmer_semitones_last = Sample::NAN;
+        let mut shimmer_phase_step = 0.0;
 
-        for (delay_left, delay_right) in self
+        // The delays always run through their whole buffer and are read behind the write position by the delay times of the size, see `read_delay`
+        let lengths =
+            GALACTIC_DELAY_TIMES.map(|time| galactic_delay_length(time, *sample_rate));
+        for ((delay_left, delay_right), &length) in self
             .delays_left
             .iter_mut()
             .zip(self.delays_right.iter_mut())
+            .zip(&lengths)
         {
-            delay_left.set_delay_length_fraction(size);
-            delay_right.set_delay_length_fraction(size);
+            delay_left.set_delay_length(length);
+            delay_right.set_delay_length(length);
         }
 
 
@@ -148,12 +462,58 @@ impl Galactic {
         //     right.set_delay_length(len);
         // }
 
-        for (((&input_sample_l, &input_sample_r), output_l), output_r) in left
+        for (f, (((&input_sample_l, &input_sample_r), output_l), output_r)) in left
             .iter()
             .zip(right.iter())
             .zip(left_out.iter_mut())
             .zip(right_out.iter_mut())
+            .enumerate()
         {
+            // A NaN replace is skipped so that it doesn't stick in the smoothing
+            if self.replace_smoothed.is_nan() {
+                self.replace_smoothed = replace[f];
+            } else if !replace[f].is_nan() {
+                self.replace_smoothed += (replace[f] - self.replace_smoothed) * replace_coeff;
+            }
+            let regen = 0.0625 + ((1.0 - self.replace_smoothed) * 0.0625);
+            // A change of size starts a crossfade of every delay from its old to its new length, which doesn't bend the pitch like a glide would
+            if self.size_fade >= 1.0 {
+                self.size_from = self.size_to;
+                let target = size_target(size[f]);
+                if target != self.size_to {
+                    self.size_to = target;
+                    self.size_fade = 0.0;
+                }
+            }
+            if self.size_fade < 1.0 {
+                self.size_fade = (self.size_fade + size_fade_step).min(1.0);
+            }
+            // No delay gets shorter than a sample, which at very low sample rates the smallest size would round down to
+            let times =
+                |size: Sample| lengths.map(|length| ((length as Sample * size) as usize).max(1));
+            let times = (times(self.size_from), times(self.size_to), self.size_fade);
+            let attenuate = (1.0 - (regen / 0.125)) * 1.333; // 1.33 if regen is high / replace is low
+            let lowpass = (1.00001 - (1.0 - brightness[f])).powi(2) / (overallscale).sqrt(); // (0.00001 + Brightness).powi(2)/overallscale.sqrt()
+            let tail_lowpass = tail_lowpass(lowpass, damping[f]);
+            let drift = detune[f].powi(3) * 0.001; // Detune.powi(3) * 0.001
+            // The smoothing snaps to the target at the end so that a mix of 1 is fully wet without any dry leaking through
+            let mix_target = mix[f].clamp(0.0, 1.0);
+            self.mix_smoothed += (mix_target - self.mix_smoothed) * mix_coeff;
+            if (mix_target - self.mix_smoothed).abs() < MIX_SNAP {
+                self.mix_smoothed = mix_target;
+            }
+            let wet = 1.0 - (1.0 - self.mix_smoothed).powi(3);
+            // The shimmer is a pitch shifted copy of the reverb output fed back into the network
+            // At its resonances the network has a gain of about 2 / (1 - 8 * regen), so the shimmer is scaled to keep the loop gain below 1 for every replace setting, see SHIMMER_GAIN
+            let shimmer_headroom = (1.0 - 8.0 * regen).max(0.0);
+            let shimmer_gain = shimmer_mix[f].clamp(0.0, 1.0) * shimmer_headroom * SHIMMER_GAIN;
+            if shimmer_semitones[f] != shimmer_semitones_last {
+                shimmer_semitones_last = shimmer_semitones[f];
+                let shimmer_ratio = (2.0 as Sample).powf(shimmer_semitones_last / 12.0);
+                // Moving the read head through the window at this speed transposes by shimmer_ratio
+                shimmer_phase_step = (shimmer_ratio - 1.0) / self.shimmer_window;
+            }
+
             // # Per sample:
             // - If the input is very faint, use the fpd values instead (floating point dither, similar to the last output sample)
 
@@ -170,13 +530,52 @@ impl Galactic {
             };
             let dry_sample_l = input_sample_l;
             let dry_sample_r = input_sample_r;
+            let (input_sample_l, input_sample_r) =
+                self.input_matrix.apply(input_sample_l, input_sample_r);
+
+            // The predelays only delay the input of the reverb, not the dry signal
+            let [smoothed_l, smoothed_r] = &mut self.predelay_smoothed;
+            *smoothed_l += (predelay_samples(predelay_left_ms[f]) - *smoothed_l) * predelay_coeff;
+            *smoothed_r += (predelay_samples(predelay_right_ms[f]) - *smoothed_r) * predelay_coeff;
+            let length = self.predelay_length;
+            let plain = if self.predelay_diffuse { PREDELAY_PLAIN_FRACTION } else { 1.0 };
+            let mut input_sample_l =
+                predelay(&mut self.predelay_left, length, input_sample_l, *smoothed_l * plain);
+            let mut input_sample_r =
+                predelay(&mut self.predelay_right, length, input_sample_r, *smoothed_r * plain);
+            if self.predelay_diffuse {
+                let [diffusers_l, diffusers_r] = &mut self.predelay_diffusers;
+                for ((allpass_l, allpass_r), fraction) in
+                    diffusers_l.iter_mut().zip(diffusers_r.iter_mut()).zip(PREDELAY_DIFFUSER_FRACTIONS)
+                {
+                    input_sample_l = allpass_l.process_modulated(
+                        input_sample_l,
+                        PREDELAY_DIFFUSER_GAIN,
+                        (*smoothed_l * fraction).max(1.0),
+                    );
+                    input_sample_r = allpass_r.process_modulated(
+                        input_sample_r,
+                        PREDELAY_DIFFUSER_GAIN,
+                        (*smoothed_r * fraction).max(1.0),
+                    );
+                }
+            }
 
             // - vibM cycles 0. - TAU, speed depending on drift (Detune) and the fpdL value last time it reset
             // vibM is phase 0-TAU, speed dpends on drift and fpd
-            self.vibM += self.oldfpd * drift as f64;
-            if self.vibM > (3.141592653589793238 * 2.0) {
-                self.vibM = 0.0;
-                self.oldfpd = 0.4294967295 + (self.fpdL as f64 * 0.0000000000618);
+            // A detune_rate above 0 sets the speed in Hz instead, without the random part
+            if detune_rate[f] > 0.0 {
+                let rate = detune_rate[f].clamp(MIN_DETUNE_RATE, MAX_DETUNE_RATE) as f64;
+                self.vibM += rate * PI * 2.0 / *sample_rate as f64;
+                if self.vibM > (PI * 2.0) {
+                    self.vibM -= PI * 2.0;
+                }
+            } else {
+                self.vibM += self.oldfpd * drift as f64;
+                if self.vibM > (PI * 2.0) {
+                    self.vibM = 0.0;
+                    self.oldfpd = 0.4294967295 + (self.fpdL as f64 * 0.0000000000618);
+                }
             }
 
             // - set the fixed size delay (256 frames) to the inputSample at the current position
@@ -186,38 +585,65 @@ impl Galactic {
                 .write_and_advance(input_sample_r * attenuate);
             // - Get a sample from the aM buffer (lin interp)
             let vibM_sin = self.vibM.sin(); // TODO: replace by something faster
-            let offsetML = ((vibM_sin) + 1.0) * 127.; // 0-256
-            let offsetMR = ((self.vibM + (3.141592653589793238 / 2.0)).sin() + 1.0) * 127.; // 0-256 90 degrees phase shifted
+            let mut offsetML = ((vibM_sin) + 1.0) * 127.; // 0-256
+            let mut offsetMR = ((self.vibM + (PI / 2.0)).sin() + 1.0) * 127.; // 0-256 90 degrees phase shifted
+            // The tape modulation takes over part of the range of the sine, so that raising its depths from 0 doesn't jump
+            let wow = wow_depth[f].clamp(0.0, 1.0);
+            let flutter = flutter_depth[f].clamp(0.0, 1.0);
+            if wow > 0.0 || flutter > 0.0 {
+                let ([tape_l, tape_r], range) = self.tape_mod.process(wow, flutter, *sample_rate);
+                let sine_range = (1.0 - range) as f64;
+                offsetML =
+                    (((vibM_sin * sine_range) + tape_l as f64 + 1.0) * 127.).clamp(0.0, 254.);
+                offsetMR = ((((self.vibM + (PI / 2.0)).sin() * sine_range) + tape_r as f64 + 1.0)
+                    * 127.)
+                    .clamp(0.0, 254.);
+            }
+            // The read position can be past the end of the buffer, `read_at_lin` wraps it and interpolates linearly between neighbouring samples, including from the last to the first. The sound of the detune depends on exactly that, see the test `read_at_lin_interpolates_and_wraps`.
             let workingML = self.detune_delay_left.position as f64 + offsetML;
             let workingMR = self.detune_delay_right.position as f64 + offsetMR;
             let input_sample_l = self.detune_delay_left.read_at_lin(workingML as Sample);
             let input_sample_r = self.detune_delay_right.read_at_lin(workingMR as Sample);
             // - Apply a lowpass filter to the output from the M delay (iirA variable)
             self.iirAL = (self.iirAL * (1.0 - lowpass)) + (input_sample_l * lowpass);
-            let input_sample_l = self.iirAL;
+            let mut input_sample_l = self.iirAL;
             self.iirAR = (self.iirAR * (1.0 - lowpass)) + (input_sample_r * lowpass);
-            let input_sample_r = self.iirAR;
+            let mut input_sample_r = self.iirAR;
             // - Only calculate a new reverb sample once every 4 samples if SR is 44100*4
 
+            if shimmer_gain > 0.0 {
+                self.shimmer_phase = (self.shimmer_phase - shimmer_phase_step).rem_euclid(1.0);
+                let shimmer_l =
+                    shimmer_read(&mut self.shimmer_delay_left, self.shimmer_phase, self.shimmer_window);
+                let shimmer_r =
+                    shimmer_read(&mut self.shimmer_delay_right, self.shimmer_phase, self.shimmer_window);
+                input_sample_l += shimmer_l * shimmer_gain;
+                input_sample_r += shimmer_r * shimmer_gain;
+            }
+
             // Reverb sample:
             // Set I-L delays for the input + respective feedback from last cycle for the opposite channel (left for right, right for left)
             // BLOCK 0
 
+            // The mono compatibility blends some of each channel's own feedback into the cross feedback, see `with_mono_compat`
+            let cross = 1.0 - 0.5 * self.mono_compat;
+            let own = 0.5 * self.mono_compat;
             for i in 0..4 {
-                self.delays_left[i].write_and_advance((self.feedback[1][i] * regen) + input_sample_l);
+                let feedback = self.feedback[1][i] * cross + self.feedback[0][i] * own;
+                self.delays_left[i].write_and_advance((feedback * regen) + input_sample_l);
             }
             for i in 0..4 {
-                self.delays_right[i]
-                    .write_and_advance((self.feedback[0][i] * regen) + input_sample_r);
+                let feedback = self.feedback[0][i] * cross + self.feedback[1][i] * own;
+                self.delays_right[i].write_and_advance((feedback * regen) + input_sample_r);
             }
 
             let mut block_0_l = [0.0; 4];
             for i in 0..4 {
-                block_0_l[i] = self.delays_left[i].read();
+                block_0_l[i] = read_delay(&mut self.delays_left[i], &lengths, i, times);
             }
             let mut block_0_r = [0.0; 4];
             for i in 0..4 {
-                block_0_r[i] = self.delays_right[i].read();
+                block_0_r[i] = read_delay(&mut self.delays_right[i], &lengths, i, times);
             }
             // BLOCK 1
 
@@ -240,11 +666,11 @@ impl Galactic {
 
             let mut block_1_l = [0.0; 4];
             for i in 0..4 {
-                block_1_l[i] = self.delays_left[i + 4].read();
+                block_1_l[i] = read_delay(&mut self.delays_left[i + 4], &lengths, i + 4, times);
             }
             let mut block_1_r = [0.0; 4];
             for i in 0..4 {
-                block_1_r[i] = self.delays_right[i + 4].read();
+                block_1_r[i] = read_delay(&mut self.delays_right[i + 4], &lengths, i + 4, times);
             }
 
             // BLOCK 2
@@ -268,11 +694,11 @@ impl Galactic {
 
             let mut block_2_l = [0.0; 4];
             for i in 0..4 {
-                block_2_l[i] = self.delays_left[i + 8].read();
+                block_2_l[i] = read_delay(&mut self.delays_left[i + 8], &lengths, i + 8, times);
             }
             let mut block_2_r = [0.0; 4];
             for i in 0..4 {
-                block_2_r[i] = self.delays_right[i + 8].read();
+                block_2_r[i] = read_delay(&mut self.delays_right[i + 8], &lengths, i + 8, times);
             }
 
 
@@ -297,30 +723,59 @@ impl Galactic {
             //
             // Apply another lowpass to the reverbed value
 
-            self.iirBL = (self.iirBL * (1.0 - lowpass)) + input_sample_l * lowpass;
+            // Darken the tail the longer it rings without new input, by lowering the cutoff of this lowpass. The lowpasses are outside of the feedback network, so this is as close to a darkening per pass as Galactic gets.
+            let levels = [
+                (dry_sample_l.abs() + dry_sample_r.abs()) * 0.5,
+                (input_sample_l.abs() + input_sample_r.abs()) * 0.5,
+            ];
+            for (follower, level) in self.darken_levels.iter_mut().zip(levels) {
+                *follower += (level - *follower) * darken_level_coeff;
+            }
+            let [input_level, tail_level] = self.darken_levels;
+            self.darken_age = if input_level < tail_level * DARKEN_INPUT_RATIO {
+                (self.darken_age + darken_age_step).min(1.0)
+            } else {
+                (self.darken_age - darken_age_step * 10.0).max(0.0)
+            };
+            let tail_lowpass =
+                tail_lowpass * (1.0 - darken[f].clamp(0.0, 1.0) * self.darken_age * DARKEN_DEPTH);
+            self.iirBL = (self.iirBL * (1.0 - tail_lowpass)) + input_sample_l * tail_lowpass;
             let mut input_sample_l = self.iirBL;
-            self.iirBR = (self.iirBR * (1.0 - lowpass)) + (input_sample_r * lowpass);
+            self.iirBR = (self.iirBR * (1.0 - tail_lowpass)) + (input_sample_r * tail_lowpass);
             let mut input_sample_r = self.iirBR;
+            self.shimmer_delay_left.write_and_advance(input_sample_l);
+            self.shimmer_delay_right.write_and_advance(input_sample_r);
 
+            // The wet gain comes after the shimmer is fed, so that it only sets the level of the output and not the loop
+            let wet_gain = self.wet_gain.next(wet_gain_db[f], gain_coeff);
+            if wet_gain != 1.0 {
+                input_sample_l *= wet_gain;
+                input_sample_r *= wet_gain;
+            }
             if wet < 1.0 {
                 input_sample_l = (input_sample_l * wet) + (dry_sample_l * (1.0 - wet));
                 input_sample_r = (input_sample_r * wet) + (dry_sample_r * (1.0 - wet));
             }
+            let gain = self.gain.next(gain_db[f], gain_coeff);
+            if gain != 1.0 {
+                input_sample_l *= gain;
+                input_sample_r *= gain;
+            }
 
-            let (_mantissa_l, exp_l) = frexp(input_sample_l as f32);
+            let (_mantissa_l, exp_l) = frexp(input_sample_l);
             let mut fpdL = self.fpdL;
             fpdL ^= fpdL << 13;
             fpdL ^= fpdL >> 17;
             fpdL ^= fpdL << 5;
-            input_sample_l += (((fpdL as f64)-(0x7fffffff_u32) as f64) * 5.5e-36 * (2_u64.pow(exp_l+62) as f64)) as Sample;
+            input_sample_l += (((fpdL as f64)-(0x7fffffff_u32) as f64) * 5.5e-36 * 2_f64.powi(exp_l + 62)) as Sample;
             self.fpdL = fpdL;
 
-            let (_mantissa_r, exp_r) = frexp(input_sample_r as f32);
+            let (_mantissa_r, exp_r) = frexp(input_sample_r);
             let mut fpdR = self.fpdR;
             fpdR ^= fpdR << 13;
             fpdR ^= fpdR >> 17;
             fpdR ^= fpdR << 5;
-            input_sample_r += (((fpdR as f64)-(0x7fffffff_u32) as f64) * 5.5e-36 * (2_u64.pow(exp_r+62) as f64)) as Sample;
+            input_sample_r += (((fpdR as f64)-(0x7fffffff_u32) as f64) * 5.5e-36 * 2_f64.powi(exp_r + 62)) as Sample;
             self.fpdR = fpdR;
 
 
@@ -331,13 +786,1671 @@ impl Galactic {
     }
 }
 
-fn frexp(s: f32) -> (f32, u32) {
+/// Galactic maps directly onto the interface: mix and size are its own mix and size inputs. The remaining inputs are left at their defaults.
+impl Reverb for Galactic {
+    fn set_mix(&mut self, mix: Sample) {
+        self.reverb_mix = mix.clamp(0.0, 1.0);
+    }
+    fn set_size(&mut self, size: Sample) {
+        self.reverb_size = size.clamp(0.0, 1.0);
+    }
+    fn reset(&mut self) {
+        Galactic::reset(self);
+    }
+    fn process_stereo(
+        &mut self,
+        left: &[Sample],
+        right: &[Sample],
+        left_out: &mut [Sample],
+        right_out: &mut [Sample],
+        sample_rate: SampleRate,
+    ) {
+        let params = Galactic::param_metadata();
+        self.reverb_controls.prepare(params, left.len());
+        for (name, value) in [("size", self.reverb_size), ("mix", self.reverb_mix)] {
+            let index = params.iter().position(|p| p.name == name).unwrap();
+            self.reverb_controls.set(index, value);
+        }
+        let controls = std::mem::take(&mut self.reverb_controls);
+        self.process(
+            left,
+            right,
+            controls.get(0),
+            controls.get(1),
+            controls.get(2),
+            controls.get(3),
+            controls.get(4),
+            controls.get(5),
+            controls.get(6),
+            controls.get(7),
+            controls.get(8),
+            controls.get(9),
+            controls.get(10),
+            controls.get(11),
+            controls.get(12),
+            controls.get(13),
+            controls.get(14),
+            controls.get(15),
+            left_out,
+            right_out,
+            sample_rate,
+        );
+        self.reverb_controls = controls;
+    }
+}
+
+/// [`Galactic`] for a mono source, upload it with `galactic_mono`. The input is fed to both channels of the reverb and the output is still stereo. The stereo image comes from the detune modulation, which is 90 degrees apart between the channels, so at a `detune` of 0 both outputs are the same.
+pub struct GalacticMono {
+    galactic: Galactic,
+}
+
+impl GalacticMono {
+    /// Number of output channels of the node, stereo like [`Galactic`]
+    pub const OUTPUT_CHANNELS: usize = 2;
+    /// Same as [`Galactic::param_metadata`], the inputs come after the mono audio input
+    pub fn param_metadata() -> &'static [ParamInfo] {
+        Galactic::param_metadata()
+    }
+    /// Same as [`Galactic::reset`]
+    pub fn reset(&mut self) {
+        self.galactic.reset();
+    }
+    /// Same as [`Galactic::with_predelay_diffuse`]
+    pub fn with_predelay_diffuse(mut self, predelay_diffuse: bool) -> Self {
+        self.galactic = self.galactic.with_predelay_diffuse(predelay_diffuse);
+        self
+    }
+    /// Same as [`Galactic::with_mono_compat`]
+    pub fn with_mono_compat(mut self, amount: Sample) -> Self {
+        self.galactic = self.galactic.with_mono_compat(amount);
+        self
+    }
+}
+
+impl Default for GalacticMono {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[impl_gen]
+impl GalacticMono {
+    pub fn new() -> Self {
+        Self {
+            galactic: Galactic::new(),
+        }
+    }
+    pub fn init(&mut self, sample_rate: SampleRate) {
+        self.galactic.init(sample_rate);
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn process(
+        &mut self,
+        input: &[Sample],
+        size: &[Sample],
+        replace: &[Sample],
+        brightness: &[Sample],
+        damping: &[Sample],
+        detune: &[Sample],
+        mix: &[Sample],
+        shimmer_mix: &[Sample],
+        shimmer_semitones: &[Sample],
+        predelay_left_ms: &[Sample],
+        predelay_right_ms: &[Sample],
+        detune_rate: &[Sample],
+        darken: &[Sample],
+        wow_depth: &[Sample],
+        flutter_depth: &[Sample],
+        gain_db: &[Sample],
+        wet_gain_db: &[Sample],
+        left_out: &mut [Sample],
+        right_out: &mut [Sample],
+        sample_rate: SampleRate,
+    ) -> GenState {
+        self.galactic.process(
+            input,
+            input,
+            size,
+            replace,
+            brightness,
+            damping,
+            detune,
+            mix,
+            shimmer_mix,
+            shimmer_semitones,
+            predelay_left_ms,
+            predelay_right_ms,
+            detune_rate,
+            darken,
+            wow_depth,
+            flutter_depth,
+            gain_db,
+            wet_gain_db,
+            left_out,
+            right_out,
+            sample_rate,
+        )
+    }
+}
+
+/// Frequency of the wow of a [`TapeMod`] in Hz
+const WOW_FREQ: Sample = 0.7;
+/// Frequency of the flutter of a [`TapeMod`] in Hz
+const FLUTTER_FREQ: Sample = 7.5;
+/// How far a depth of 1 moves the detune read head, as a fraction of the 127 samples it can move each way. At 44.1 kHz this is a pitch deviation of about 1% for the wow and 0.8% for the flutter.
+const WOW_RANGE: Sample = 0.8;
+const FLUTTER_RANGE: Sample = 0.06;
+/// Cutoff of the lowpass on the noise of the flutter and its level relative to the flutter sine
+const FLUTTER_NOISE_FREQ: Sample = 12.0;
+const FLUTTER_NOISE: Sample = 0.5;
+
+/// Tape style wow and flutter for the detune modulation of [`Galactic`], set with its `wow_depth` and `flutter_depth` inputs. The wow is a slow sine and the flutter a faster one with lowpassed noise on top, so that it never repeats exactly. The right channel is a quarter cycle behind the left, like the detune sine, and both share the noise.
+pub struct TapeMod {
+    /// Phases of the wow and flutter sines, 0-1
+    wow_phase: Sample,
+    flutter_phase: Sample,
+    /// Lowpassed noise with a standard deviation of about 0.5
+    noise: Sample,
+    rng: XOrShift32Rng,
+}
+
+impl TapeMod {
+    /// A tape modulation with noise picked from `seed`
+    pub fn new(seed: u64) -> Self {
+        Self {
+            wow_phase: 0.0,
+            flutter_phase: 0.0,
+            noise: 0.0,
+            rng: crate::luffverb::seeded_rng(seed),
+        }
+    }
+    /// Advance by one sample. Returns the offset of the left and right read heads as a fraction of their range, and the range they can take up, which the detune sine is scaled down to make room for. `wow_depth` and `flutter_depth` go from 0 to 1.
+    pub fn process(
+        &mut self,
+        wow_depth: Sample,
+        flutter_depth: Sample,
+        sample_rate: Sample,
+    ) -> ([Sample; 2], Sample) {
+        use std::f32::consts::TAU;
+        self.wow_phase = (self.wow_phase + WOW_FREQ / sample_rate).fract();
+        self.flutter_phase = (self.flutter_phase + FLUTTER_FREQ / sample_rate).fract();
+        let noise_coeff = 1.0 - (-TAU * FLUTTER_NOISE_FREQ / sample_rate).exp();
+        // Scales uniform noise with a variance of 1/3 to a variance of about 0.25 after the lowpass
+        let white = (self.rng.gen_f32() as Sample * 2.0 - 1.0) * (1.5 / noise_coeff).sqrt();
+        self.noise += (white - self.noise) * noise_coeff;
+        let wow = wow_depth * WOW_RANGE;
+        let flutter = flutter_depth * FLUTTER_RANGE;
+        let noise = self.noise.clamp(-1.0, 1.0) * FLUTTER_NOISE * flutter;
+        let offset = |quarter: Sample| {
+            wow * ((self.wow_phase - quarter) * TAU).sin()
+                + flutter * ((self.flutter_phase - quarter) * TAU).sin()
+                + noise
+        };
+        ([offset(0.0), offset(0.25)], wow + flutter * (1.0 + FLUTTER_NOISE))
+    }
+}
+
+/// A gain set in decibels, which is only converted to linear when it changes and is smoothed in linear terms
+struct DbGain {
+    /// Last value set and its linear gain
+    db: Sample,
+    linear: Sample,
+    /// Smoothed linear gain, NaN until the first sample
+    smoothed: Sample,
+}
+
+impl DbGain {
+    fn new() -> Self {
+        Self {
+            db: 0.0,
+            linear: 1.0,
+            smoothed: Sample::NAN,
+        }
+    }
+    fn reset(&mut self) {
+        self.smoothed = Sample::NAN;
+    }
+    /// Advance by one sample towards `db`, clamped to [`MAX_GAIN_DB`], and return the linear gain
+    fn next(&mut self, db: Sample, coeff: Sample) -> Sample {
+        let db = db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+        if db != self.db {
+            self.db = db;
+            self.linear = (10.0 as Sample).powf(db / 20.0);
+        }
+        if self.smoothed.is_nan() {
+            self.smoothed = self.linear;
+        }
+        self.smoothed += (self.linear - self.smoothed) * coeff;
+        if (self.linear - self.smoothed).abs() < MIX_SNAP * self.linear {
+            self.smoothed = self.linear;
+        }
+        self.smoothed
+    }
+}
+
+/// Speed of sound in dry air at 20°C in m/s
+const SPEED_OF_SOUND: Sample = 343.0;
+/// The delay lengths of Galactic are in samples at this rate, as in the original plugin
+const GALACTIC_SAMPLE_RATE: Sample = 44100.0;
+
+/// The `size` of a [`Galactic`] for a room of roughly `meters` across.
+///
+/// `size` scales every delay of the reverb linearly, from 10% of their full length at 0 to the full length at 1. The mapping makes the longest delay the time it takes sound to cross the room and come back, `2 * meters / 343` seconds. At full size the longest delay is 345 ms, so rooms up to about 6 m map to 0 and rooms above about 59 m to 1.
+///
+/// The delays are counted in samples at 44.1 kHz, so at higher sample rates the same `size` is a proportionally smaller room.
+pub fn galactic_size_from_meters(meters: Sample) -> Sample {
+    let longest_delay = *GALACTIC_DELAY_TIMES.iter().max().unwrap() as Sample / GALACTIC_SAMPLE_RATE;
+    let fraction = 2.0 * meters / SPEED_OF_SOUND / longest_delay;
+    ((fraction - 0.1) / 0.9).clamp(0.0, 1.0)
+}
+
+/// Damping darkens the tail (iirB) relative to the input filter (iirA), from following brightness like in the original at 0 to 1/100 of its coefficient at 1.
+fn tail_lowpass(lowpass: Sample, damping: Sample) -> Sample {
+    lowpass * (1.0 - damping.clamp(0.0, 1.0) * 0.9).powi(2)
+}
+
+/// Read delay `index` of the network, which has a buffer of `lengths[index]` samples, right after writing to it. `times` holds the delay times of the size being crossfaded from, those of the size being crossfaded to and the progress of the crossfade. Both are whole samples, so a constant size reads exactly the samples it always did.
+fn read_delay(
+    delay: &mut StaticSampleDelay,
+    lengths: &[usize; 12],
+    index: usize,
+    (from, to, fade): ([usize; 12], [usize; 12], Sample),
+) -> Sample {
+    let length = lengths[index];
+    let mut read = |time: usize| delay.read_at((delay.position + length - time) % length);
+    let new = read(to[index]);
+    if fade >= 1.0 {
+        return new;
+    }
+    let old = read(from[index]);
+    old + (new - old) * fade
+}
+
+/// Read two taps half a window apart from a pitch shifting delay, crossfading between them with triangular windows so that the jump when a tap wraps around is silent.
+fn shimmer_read(delay: &mut StaticSampleDelay, phase: Sample, window: Sample) -> Sample {
+    let buffer_length = window + 2.0;
+    let mut out = 0.0;
+    for phase in [phase, (phase + 0.5) % 1.0] {
+        let gain = 1.0 - (2.0 * phase - 1.0).abs();
+        let delay_time = 1.0 + phase * window;
+        out += delay.read_at_lin(delay.position as Sample + buffer_length - delay_time) * gain;
+    }
+    out
+}
+
+fn frexp(s: f32) -> (f32, i32) {
     if 0.0 == s {
-        return (s, 0);
+        (s, 0)
     } else {
         let lg = s.abs().log2();
         let x = (lg - lg.floor() - 1.0).exp2();
         let exp = lg.floor() + 1.0;
-        (s.signum() * x, exp as u32)
+        (s.signum() * x, exp as i32)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn param_metadata_matches_inputs() {
+        use knyst::gen::Gen;
+        let galactic = Galactic::new();
+        let inputs: Vec<_> = (0..galactic.num_inputs()).map(|i| galactic.input_desc(i)).collect();
+        let names: Vec<_> = Galactic::param_metadata().iter().map(|p| p.name).collect();
+        assert_eq!(&inputs[2..], &names);
+        for param in Galactic::param_metadata() {
+            assert!(param.min <= param.default && param.default <= param.max);
+        }
+        let mono = GalacticMono::new();
+        let inputs: Vec<_> = (0..mono.num_inputs()).map(|i| mono.input_desc(i)).collect();
+        assert_eq!(&inputs[1..], &names);
+    }
+
+    #[test]
+    fn output_channels_match_the_gen() {
+        use knyst::gen::Gen;
+        assert_eq!(Galactic::new().num_outputs(), Galactic::OUTPUT_CHANNELS);
+        assert_eq!(GalacticMono::new().num_outputs(), GalacticMono::OUTPUT_CHANNELS);
+    }
+
+    #[test]
+    fn parameters_change_within_a_block() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let mut galactic = Galactic::new();
+        galactic.init(sample_rate);
+        let control = |value: Sample| vec![value; block_size];
+        // Fully dry for the first half of the block, fully wet for the second half
+        let mix: Vec<Sample> = (0..block_size).map(|f| if f < 32 { 0.0 } else { 1.0 }).collect();
+        let input = control(0.5);
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        galactic.process(
+            &input,
+            &input,
+            &control(1.0),
+            &control(0.5),
+            &control(0.5),
+            &control(0.0),
+            &control(0.5),
+            &mix,
+            &control(0.0),
+            &control(0.0),
+            &control(0.0),
+            &control(0.0),
+            &control(0.0),
+            &control(0.0),
+            &control(0.0),
+            &control(0.0),
+            &control(0.0),
+            &control(0.0),
+            &mut left_out,
+            &mut right_out,
+            sample_rate,
+        );
+        assert!(left_out[..32].iter().all(|s| (s - 0.5).abs() < 1e-6));
+        // The mix is smoothed, but it starts moving towards wet right away
+        assert!(left_out[33] < 0.5);
+        assert!(left_out[63] < 0.45);
+    }
+
+    #[test]
+    fn full_mix_is_fully_wet() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let mut galactic = Galactic::new();
+        galactic.init(sample_rate);
+        let control = |value: Sample| vec![value; block_size];
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        // Start half wet so that the mix has to glide to 1, then send an impulse which the predelay keeps out of the reverb for 50 ms
+        for block in 0..1000 {
+            let mut input = control(0.0);
+            let impulse = block == 999;
+            input[0] = if impulse { 1.0 } else { 0.0 };
+            galactic.process(
+                &input,
+                &input,
+                &control(1.0),
+                &control(0.5),
+                &control(0.5),
+                &control(0.0),
+                &control(0.5),
+                &control(if block < 10 { 0.5 } else { 1.0 }),
+                &control(0.0),
+                &control(0.0),
+                &control(50.0),
+                &control(50.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &mut left_out,
+                &mut right_out,
+                sample_rate,
+            );
+        }
+        assert_eq!(galactic.mix_smoothed, 1.0);
+        assert!(left_out.iter().chain(&right_out).all(|s| s.abs() < 1e-6));
+    }
+
+    #[test]
+    fn mix_step_does_not_click() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let mut galactic = Galactic::new();
+        galactic.init(sample_rate);
+        let control = |value: Sample| vec![value; block_size];
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        let mut output = Vec::new();
+        for block in 0..20 {
+            let input: Vec<Sample> = (0..block_size)
+                .map(|f| ((block * block_size + f) as Sample * 0.06).sin() * 0.5)
+                .collect();
+            galactic.process(
+                &input,
+                &input,
+                &control(1.0),
+                &control(0.5),
+                &control(0.5),
+                &control(0.0),
+                &control(0.5),
+                &control(if block < 10 { 0.0 } else { 1.0 }),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &mut left_out,
+                &mut right_out,
+                sample_rate,
+            );
+            output.extend_from_slice(&left_out);
+        }
+        // The steepest slope of the dry sine is 0.03 per sample
+        let max_step = output.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0.0, Sample::max);
+        assert!(max_step < 0.05, "{max_step}");
+    }
+
+    #[test]
+    fn constant_mix_is_not_smoothed() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let mut galactic = Galactic::new();
+        galactic.init(sample_rate);
+        let control = |value: Sample| vec![value; block_size];
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        for _ in 0..3 {
+            galactic.process(
+                &control(0.5),
+                &control(0.5),
+                &control(1.0),
+                &control(0.5),
+                &control(0.5),
+                &control(0.0),
+                &control(0.5),
+                &control(0.3),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &mut left_out,
+                &mut right_out,
+                sample_rate,
+            );
+            // The smoothing starts at the first mix it sees, so a mix that never changes is used as it is from the first sample
+            assert_eq!(galactic.mix_smoothed, 0.3);
+        }
+    }
+
+    #[test]
+    fn reset_keeps_the_dither_running() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let mut galactic = Galactic::new();
+        galactic.init(sample_rate);
+        let control = |value: Sample| vec![value; block_size];
+        let impulse_response = |galactic: &mut Galactic| {
+            galactic.reset();
+            let mut output = Vec::new();
+            let mut left_out = vec![0.0; block_size];
+            let mut right_out = vec![0.0; block_size];
+            for block in 0..200 {
+                let mut input = control(0.0);
+                input[0] = if block == 0 { 1.0 } else { 0.0 };
+                galactic.process(
+                    &input,
+                    &input,
+                    &control(0.5),
+                    &control(0.5),
+                    &control(0.5),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                output.extend_from_slice(&left_out);
+            }
+            output
+        };
+        let first = impulse_response(&mut galactic);
+        let seeds = (galactic.fpdL, galactic.fpdR);
+        let second = impulse_response(&mut galactic);
+        assert_ne!((galactic.fpdL, galactic.fpdR), seeds);
+        // The dither differs, and so does the faint noise Galactic feeds itself instead of a silent input, but both stay below -120 dB
+        assert_ne!(first, second);
+        let peak = first.iter().fold(0.0 as Sample, |peak, s| peak.max(s.abs()));
+        assert!(peak > 1e-3);
+        assert!(first.iter().zip(&second).all(|(a, b)| (a - b).abs() < 1e-6));
+    }
+
+    #[test]
+    fn predelay_diffuse_smears_the_onset() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        // Returns the left output for an impulse in both channels with a predelay of 100 ms
+        let render = |predelay_diffuse: bool| {
+            let mut galactic = Galactic::new().with_predelay_diffuse(predelay_diffuse);
+            (galactic.fpdL, galactic.fpdR) = (16386, 16386);
+            galactic.init(sample_rate);
+            let mut output = Vec::new();
+            let mut left_out = vec![0.0; block_size];
+            let mut right_out = vec![0.0; block_size];
+            for block in 0..200 {
+                let mut input = control(0.0);
+                input[0] = if block == 0 { 1.0 } else { 0.0 };
+                galactic.process(
+                    &input,
+                    &input,
+                    &control(1.0),
+                    &control(0.5),
+                    &control(0.5),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(100.0),
+                    &control(100.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                output.extend_from_slice(&left_out);
+            }
+            output
+        };
+        let plain = render(false);
+        let diffused = render(true);
+        let peak = plain.iter().fold(0.0 as Sample, |peak, s| peak.max(s.abs()));
+        let onset = |channel: &[Sample]| channel.iter().position(|s| s.abs() > peak * 1e-3).unwrap();
+        let energy = |channel: &[Sample]| channel.iter().map(|s| s * s).sum::<Sample>();
+        // The plain predelay holds everything back for 100 ms, 4410 samples
+        assert!(onset(&plain) >= 4410);
+        // The diffused predelay lets the first echo through after a fifth of it
+        let plain_onset = onset(&plain);
+        assert!(onset(&diffused) < plain_onset - 2000);
+        // and spreads part of the energy over the rest of the predelay, without adding or losing much
+        let early = energy(&diffused[..plain_onset]);
+        assert!(early > energy(&diffused) * 0.05);
+        let ratio = energy(&diffused) / energy(&plain);
+        assert!(ratio > 0.5 && ratio < 2.0);
+    }
+
+    #[test]
+    fn predelay_delays_the_reverb() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        // Returns the left and right output for an impulse in both channels
+        let render = |fpd: (u32, u32), predelay_ms: [Sample; 2]| {
+            let mut galactic = Galactic::new();
+            (galactic.fpdL, galactic.fpdR) = fpd;
+            galactic.init(sample_rate);
+            let mut outputs = [Vec::new(), Vec::new()];
+            let mut left_out = vec![0.0; block_size];
+            let mut right_out = vec![0.0; block_size];
+            for block in 0..100 {
+                let mut input = control(0.0);
+                input[0] = if block == 0 { 1.0 } else { 0.0 };
+                // No detune, so that the modulation doesn't depend on when the input arrives
+                galactic.process(
+                    &input,
+                    &input,
+                    &control(1.0),
+                    &control(0.5),
+                    &control(0.5),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(predelay_ms[0]),
+                    &control(predelay_ms[1]),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                outputs[0].extend_from_slice(&left_out);
+                outputs[1].extend_from_slice(&right_out);
+            }
+            outputs
+        };
+        let galactic = Galactic::new();
+        let fpd = (galactic.fpdL, galactic.fpdR);
+        let [left, right] = render(fpd, [0.0, 0.0]);
+        let peak = left.iter().fold(0.0 as Sample, |peak, s| peak.max(s.abs()));
+        let onset =
+            |channel: &[Sample]| channel.iter().position(|s| s.abs() > peak * 1e-3).unwrap();
+        // 10 ms is 441 samples
+        let [delayed_left, delayed_right] = render(fpd, [10.0, 10.0]);
+        for (original, delayed) in [(&left, &delayed_left), (&right, &delayed_right)] {
+            assert!(delayed[..441].iter().all(|s| s.abs() < peak * 1e-3));
+            for (o, d) in original.iter().zip(&delayed[441..]) {
+                assert!((o - d).abs() < peak * 1e-3);
+            }
+        }
+        // Different predelays per channel shift the channels apart
+        let [shifted_left, shifted_right] = render(fpd, [0.0, 10.0]);
+        assert_eq!(onset(&shifted_left), onset(&left));
+        assert_eq!(onset(&shifted_right), onset(&right) + 441);
+    }
+
+    #[test]
+    fn block_size_does_not_change_the_output() {
+        let sample_rate = SampleRate::from(44100.);
+        let mut galactic = Galactic::new();
+        galactic.init(sample_rate);
+        // The dither and the detune modulation depend on the random state, which is restored for the second run
+        let state = (galactic.fpdL, galactic.fpdR, galactic.oldfpd, galactic.vibM);
+        let mut rng = fastrand::Rng::with_seed(1);
+        let input: Vec<Sample> = (0..1024 * 8).map(|_| rng.f32() * 2.0 - 1.0).collect();
+        let mut outputs = [Vec::new(), Vec::new()];
+        for (block_size, output) in [1024, 64].into_iter().zip(outputs.iter_mut()) {
+            Reverb::reset(&mut galactic);
+            (galactic.fpdL, galactic.fpdR, galactic.oldfpd, galactic.vibM) = state;
+            let control = |value: Sample| vec![value; block_size];
+            let mut left_out = vec![0.0; block_size];
+            let mut right_out = vec![0.0; block_size];
+            for block in input.chunks(block_size) {
+                galactic.process(
+                    block,
+                    block,
+                    &control(1.0),
+                    &control(0.5),
+                    &control(0.5),
+                    &control(0.0),
+                    &control(0.5),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                output.extend_from_slice(&left_out);
+            }
+        }
+        let [big_blocks, small_blocks] = outputs;
+        assert!(big_blocks.iter().any(|&s| s != 0.0));
+        assert_eq!(big_blocks, small_blocks);
+    }
+
+    #[test]
+    fn mono_feeds_both_channels() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        let mut mono = GalacticMono::new();
+        mono.init(sample_rate);
+        let mut galactic = Galactic::new();
+        galactic.init(sample_rate);
+        (galactic.fpdL, galactic.fpdR) = (mono.galactic.fpdL, mono.galactic.fpdR);
+        let mut outputs: [[Vec<Sample>; 2]; 2] =
+            std::array::from_fn(|_| std::array::from_fn(|_| vec![0.0; block_size]));
+        let mut differs = false;
+        for block in 0..100 {
+            let mut input = control(0.0);
+            input[0] = if block == 0 { 1.0 } else { 0.0 };
+            let [[mono_left, mono_right], [left, right]] = &mut outputs;
+            mono.process(
+                &input,
+                &control(1.0),
+                &control(0.5),
+                &control(0.5),
+                &control(0.0),
+                &control(0.5),
+                &control(1.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                mono_left,
+                mono_right,
+                sample_rate,
+            );
+            galactic.process(
+                &input,
+                &input,
+                &control(1.0),
+                &control(0.5),
+                &control(0.5),
+                &control(0.0),
+                &control(0.5),
+                &control(1.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                left,
+                right,
+                sample_rate,
+            );
+            assert_eq!(mono_left, left);
+            assert_eq!(mono_right, right);
+            differs |= mono_left != mono_right;
+        }
+        // The detune modulation makes the output stereo
+        assert!(differs);
+    }
+
+    #[test]
+    fn detune_rate_sets_the_vibrato_speed() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 4410;
+        let control = |value: Sample| vec![value; block_size];
+        let vibrato_phase = |oldfpd: f64, rate: Sample| {
+            let mut galactic = Galactic::new();
+            galactic.init(sample_rate);
+            (galactic.oldfpd, galactic.vibM) = (oldfpd, 0.0);
+            let mut left_out = control(0.0);
+            let mut right_out = control(0.0);
+            galactic.process(
+                &control(0.0),
+                &control(0.0),
+                &control(1.0),
+                &control(0.5),
+                &control(0.5),
+                &control(0.0),
+                &control(0.5),
+                &control(1.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(rate),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &mut left_out,
+                &mut right_out,
+                sample_rate,
+            );
+            galactic.vibM
+        };
+        // 0.1 s at 2 Hz is a fifth of a cycle, whatever the random state
+        let expected = std::f64::consts::TAU * 0.2;
+        for oldfpd in [0.43, 0.6] {
+            assert!((vibrato_phase(oldfpd, 2.0) - expected).abs() < 1e-3);
+        }
+        // Out of range rates are clamped
+        assert_eq!(vibrato_phase(0.5, 100.0), vibrato_phase(0.5, MAX_DETUNE_RATE));
+        // Not driven, the speed still comes from detune and the random state
+        assert!(vibrato_phase(0.43, 0.0) != vibrato_phase(0.6, 0.0));
+    }
+
+    #[test]
+    fn size_from_meters() {
+        let longest_delay = *GALACTIC_DELAY_TIMES.iter().max().unwrap() as Sample;
+        for meters in [10.0 as Sample, 20.0, 40.0] {
+            let size = galactic_size_from_meters(meters);
+            let delay_seconds = (size * 0.9 + 0.1) * longest_delay / 44100.0;
+            assert!((delay_seconds - 2.0 * meters / 343.0).abs() < 1e-4);
+        }
+        assert!(galactic_size_from_meters(10.0) < galactic_size_from_meters(20.0));
+        assert_eq!(galactic_size_from_meters(1.0), 0.0);
+        assert_eq!(galactic_size_from_meters(100.0), 1.0);
+    }
+
+    #[test]
+    fn darken_darkens_the_fading_tail() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 441;
+        let control = |value: Sample| vec![value; block_size];
+        let reference = Galactic::new();
+        // Ratio of the energy of the first difference to the energy of the output, which goes down as the output gets darker, for the second before and the second after 2 s of silence
+        let brightness = |darken: Sample| {
+            let mut galactic = Galactic::new();
+            galactic.init(sample_rate);
+            (galactic.fpdL, galactic.fpdR) = (reference.fpdL, reference.fpdR);
+            let mut rng = fastrand::Rng::with_seed(1);
+            let mut left_out = control(0.0);
+            let mut right_out = control(0.0);
+            let mut output = Vec::new();
+            for block in 0..400 {
+                let input: Vec<Sample> = (0..block_size)
+                    .map(|_| if block < 100 { rng.f32() * 2.0 - 1.0 } else { 0.0 })
+                    .collect();
+                galactic.process(
+                    &input,
+                    &input,
+                    &control(1.0),
+                    &control(0.5),
+                    &control(0.8),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(darken),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                output.extend_from_slice(&left_out);
+            }
+            let ratio = |samples: &[Sample]| {
+                let difference: Sample = samples.windows(2).map(|w| (w[1] - w[0]).powi(2)).sum();
+                difference / samples.iter().map(|s| s * s).sum::<Sample>()
+            };
+            [ratio(&output[0..44100]), ratio(&output[132300..])]
+        };
+        let [driven, fading] = brightness(0.0);
+        let [darkened_driven, darkened_fading] = brightness(1.0);
+        // While there is input the darkening doesn't do anything
+        assert!((darkened_driven / driven - 1.0).abs() < 0.01);
+        assert!(darkened_fading < fading * 0.5);
     }
+
+    #[test]
+    fn tape_mod_profile() {
+        let sample_rate = 44100.0;
+        // Returns the left offsets over 10 seconds and the range the tape modulation reports
+        let run = |wow: Sample, flutter: Sample| {
+            let mut tape = TapeMod::new(1);
+            let mut range = 0.0;
+            let offsets: Vec<Sample> = (0..441000)
+                .map(|_| {
+                    let ([left, _], r) = tape.process(wow, flutter, sample_rate);
+                    range = r;
+                    left
+                })
+                .collect();
+            (offsets, range)
+        };
+        let crossings = |offsets: &[Sample]| {
+            offsets.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count()
+        };
+        let (wow, wow_range) = run(1.0, 0.0);
+        assert_eq!(wow_range, WOW_RANGE);
+        assert!(wow.iter().all(|o| o.abs() <= wow_range));
+        // Two crossings per cycle of the wow
+        assert!((crossings(&wow) as i32 - 14).abs() <= 1);
+        let (flutter, flutter_range) = run(0.0, 1.0);
+        assert!(flutter.iter().all(|o| o.abs() <= flutter_range));
+        assert!(crossings(&flutter) > 100);
+        // The noise keeps one flutter cycle from repeating the next
+        let period = (sample_rate / FLUTTER_FREQ) as usize;
+        let difference: Sample =
+            (0..period).map(|i| (flutter[i + period] - flutter[i]).abs()).sum();
+        assert!(difference / period as Sample > FLUTTER_RANGE * 0.05);
+    }
+
+    #[test]
+    fn tape_mod_warbles_the_pitch() {
+        use rustfft::{num_complex::Complex, FftPlanner};
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let fft_size = 16384;
+        let control = |value: Sample| vec![value; block_size];
+        // Fraction of the energy of the output within 5 Hz of a 1 kHz input sine, after the reverb has filled up
+        let purity = |wow: Sample, flutter: Sample| {
+            let mut galactic = Galactic::new();
+            galactic.init(sample_rate);
+            galactic.tape_mod = TapeMod::new(1);
+            let mut output = Vec::new();
+            let mut phase: Sample = 0.0;
+            let [mut left, mut right] = [control(0.0), control(0.0)];
+            for _ in 0..(44100 + fft_size) / block_size {
+                let input: Vec<Sample> = (0..block_size)
+                    .map(|_| {
+                        phase = (phase + 1000.0 / 44100.0).fract();
+                        (phase * std::f32::consts::TAU).sin()
+                    })
+                    .collect();
+                galactic.process(
+                    &input,
+                    &input,
+                    &control(0.5),
+                    &control(1.0),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(wow),
+                    &control(flutter),
+                    &control(0.0),
+                    &control(0.0),
+                    &mut left,
+                    &mut right,
+                    sample_rate,
+                );
+                output.extend_from_slice(&left);
+            }
+            let start = output.len() - fft_size;
+            // A Hann window keeps the leakage of the sine out of the neighbouring bins
+            let hann = |i: usize| {
+                0.5 - 0.5 * (std::f32::consts::TAU * i as Sample / fft_size as Sample).cos()
+            };
+            let mut spectrum: Vec<Complex<Sample>> = output[start..]
+                .iter()
+                .enumerate()
+                .map(|(i, &s)| Complex::new(s * hann(i), 0.0))
+                .collect();
+            FftPlanner::new().plan_fft_forward(fft_size).process(&mut spectrum);
+            let bin_hz = 44100.0 / fft_size as Sample;
+            let energy = |range: std::ops::Range<Sample>| -> Sample {
+                spectrum[..fft_size / 2]
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| range.contains(&(*i as Sample * bin_hz)))
+                    .map(|(_, c)| c.norm_sqr())
+                    .sum()
+            };
+            energy(995.0..1005.0) / energy(0.0..22050.0)
+        };
+        let clean = purity(0.0, 0.0);
+        assert!(clean > 0.99);
+        // The window is a quarter of a wow cycle long, so the wow only bends the pitch part of the way
+        assert!(purity(1.0, 0.0) < clean * 0.9);
+        assert!(purity(0.0, 1.0) < clean * 0.6);
+    }
+
+    #[test]
+    fn output_is_finite_for_a_parameter_sweep() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        let grid = [0.0, 0.5, 1.0];
+        let mut rng = XOrShift32Rng::new(1);
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        let mut peak: Sample = 0.0;
+        for size in grid {
+            for replace in grid {
+                for brightness in grid {
+                    for detune in grid {
+                        for mix in [0.5, 1.0] {
+                            let mut galactic = Galactic::new();
+                            galactic.init(sample_rate);
+                            // A quarter of a second of full scale white noise
+                            for _ in 0..172 {
+                                let left: Vec<Sample> =
+                                    (0..block_size).map(|_| rng.gen_f32() * 2.0 - 1.0).collect();
+                                let right: Vec<Sample> =
+                                    (0..block_size).map(|_| rng.gen_f32() * 2.0 - 1.0).collect();
+                                galactic.process(
+                                    &left,
+                                    &right,
+                                    &control(size),
+                                    &control(replace),
+                                    &control(brightness),
+                                    &control(0.0),
+                                    &control(detune),
+                                    &control(mix),
+                                    &control(0.0),
+                                    &control(12.0),
+                                    &control(0.0),
+                                    &control(0.0),
+                                    &control(0.0),
+                                    &control(0.0),
+                                    &control(0.0),
+                                    &control(0.0),
+                                    &control(0.0),
+                                    &control(0.0),
+                                    &mut left_out,
+                                    &mut right_out,
+                                    sample_rate,
+                                );
+                                for &s in left_out.iter().chain(&right_out) {
+                                    assert!(s.is_finite());
+                                    peak = peak.max(s.abs());
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // The input peaks at 1, the loudest settings peak at about 1.8
+        assert!(peak < 4.0);
+    }
+
+    #[test]
+    fn extreme_sizes_are_clamped() {
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        let mut rng = XOrShift32Rng::new(1);
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        // At 400 Hz the shortest delay is 6 samples, which the smallest size used to round down to 0. The brightness is 0 there because a higher one is too bright a lowpass for such a low sample rate.
+        for (sample_rate, brightness) in [(400.0, 0.0), (44100.0, 0.5)] {
+            let sample_rate = SampleRate::from(sample_rate);
+            let mut galactic = Galactic::new();
+            galactic.init(sample_rate);
+            let mut peak: Sample = 0.0;
+            for size in [0.0, -1.0, Sample::NAN, 1e-9, 2.0, Sample::INFINITY, 0.0] {
+                for _ in 0..50 {
+                    let input: Vec<Sample> =
+                        (0..block_size).map(|_| rng.gen_f32() * 2.0 - 1.0).collect();
+                    galactic.process(
+                        &input,
+                        &input,
+                        &control(size),
+                        &control(0.5),
+                        &control(brightness),
+                        &control(0.0),
+                        &control(0.5),
+                        &control(0.5),
+                        &control(0.0),
+                        &control(12.0),
+                        &control(0.0),
+                        &control(0.0),
+                        &control(0.0),
+                        &control(0.0),
+                        &control(0.0),
+                        &control(0.0),
+                        &control(0.0),
+                        &control(0.0),
+                        &mut left_out,
+                        &mut right_out,
+                        sample_rate,
+                    );
+                    for &s in left_out.iter().chain(&right_out) {
+                        assert!(s.is_finite());
+                        peak = peak.max(s.abs());
+                    }
+                }
+            }
+            assert!(peak > 0.0 && peak < 4.0);
+        }
+    }
+
+    #[test]
+    fn gain_db_scales_the_output() {
+        let sample_rate = SampleRate::from(44100.0);
+        let block_size = 64;
+        let run = |gain_db: Sample| {
+            let mut galactic = Galactic::new();
+            galactic.init(sample_rate);
+            (galactic.fpdL, galactic.fpdR) = (16386, 16386);
+            let control = |value: Sample| vec![value; block_size];
+            let mut output = Vec::new();
+            let mut left_out = vec![0.0; block_size];
+            let mut right_out = vec![0.0; block_size];
+            for block in 0..200 {
+                let mut input = vec![0.0; block_size];
+                if block == 0 {
+                    input[0] = 0.5;
+                }
+                galactic.process(
+                    &input,
+                    &input,
+                    &control(0.5),
+                    &control(0.5),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.5),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(gain_db),
+                    &control(0.0),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                output.extend_from_slice(&left_out);
+            }
+            output
+        };
+        let unity = run(0.0);
+        let boosted = run(20.0 * (2.0 as Sample).log10());
+        let cut = run(-MAX_GAIN_DB * 4.0);
+        let peak = |output: &[Sample]| output.iter().fold(0.0, |peak: Sample, s| peak.max(s.abs()));
+        assert!(peak(&unity) > 0.0);
+        // The dither is tiny next to the signal, so doubling the gain doubles the output up to it
+        let tolerance = 1e-3 * peak(&unity);
+        for (unity, boosted) in unity.iter().zip(&boosted) {
+            assert!((boosted - unity * 2.0).abs() < tolerance, "{boosted} {unity}");
+        }
+        // The gain is clamped to the range of the input
+        let floor = (10.0 as Sample).powf(-MAX_GAIN_DB / 20.0);
+        assert!((peak(&cut) / peak(&unity) - floor).abs() < 1e-3, "{}", peak(&cut) / peak(&unity));
+    }
+
+    #[test]
+    fn read_at_lin_interpolates_and_wraps() {
+        // The detune delay reads a 256 sample buffer at its position plus an offset of up to 254
+        let mut delay = StaticSampleDelay::new(256);
+        for i in 0..256 {
+            delay.write_and_advance(i as Sample);
+        }
+        assert_eq!(delay.position, 0);
+        // Within the buffer every sample holds its index, so a read gives back the index
+        for index in [0.0 as Sample, 0.25, 10.5, 100.75, 254.9] {
+            assert!((delay.read_at_lin(index) - index).abs() < 1e-4);
+        }
+        // Between the last and the first sample
+        assert!((delay.read_at_lin(255.25) - 255.0 * 0.75).abs() < 1e-4);
+        // Indices past the end wrap around
+        assert!((delay.read_at_lin(256.0 + 10.5) - 10.5).abs() < 1e-4);
+        assert!((delay.read_at_lin(2.0 * 256.0 + 3.25) - 3.25).abs() < 1e-4);
+        // Reading doesn't advance the delay
+        assert_eq!(delay.position, 0);
+    }
+
+    #[test]
+    fn mono_compat_narrows_the_tail() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        // Returns the mid and side of the output for an impulse in the left input
+        let render = |mono_compat: Sample| {
+            let mut galactic = Galactic::new().with_mono_compat(mono_compat);
+            (galactic.fpdL, galactic.fpdR) = (16386, 16386);
+            galactic.init(sample_rate);
+            let (mut mid, mut side) = (Vec::new(), Vec::new());
+            let mut left_out = vec![0.0; block_size];
+            let mut right_out = vec![0.0; block_size];
+            for block in 0..1000 {
+                let mut left = control(0.0);
+                left[0] = if block == 0 { 1.0 } else { 0.0 };
+                galactic.process(
+                    &left,
+                    &control(0.0),
+                    &control(0.5),
+                    &control(0.5),
+                    &control(0.5),
+                    &control(0.0),
+                    &control(0.5),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                for (l, r) in left_out.iter().zip(&right_out) {
+                    mid.push(l + r);
+                    side.push(l - r);
+                }
+            }
+            (mid, side)
+        };
+        let energy = |signal: &[Sample]| signal.iter().map(|s| s * s).sum::<Sample>();
+        // The second half of the first second, once the tail has built up
+        let tail = 22050..44100;
+        let (wide_mid, wide_side) = render(0.0);
+        let (narrow_mid, narrow_side) = render(1.0);
+        let wide_ratio = energy(&wide_side[tail.clone()]) / energy(&wide_mid[tail.clone()]);
+        let narrow_ratio = energy(&narrow_side[tail.clone()]) / energy(&narrow_mid[tail.clone()]);
+        assert!(narrow_ratio < wide_ratio * 0.5);
+        // The sum of the channels is unchanged, and still decays
+        let wide_energy = energy(&wide_mid[tail.clone()]);
+        assert!((energy(&narrow_mid[tail.clone()]) / wide_energy - 1.0).abs() < 0.01);
+        let late = 44100 * 13 / 10..64000;
+        assert!(energy(&narrow_mid[late.clone()]) < energy(&narrow_mid[tail.clone()]) * 0.5);
+        assert!(narrow_mid.iter().all(|s| s.is_finite()));
+    }
+
+    #[test]
+    fn input_matrix_routes_the_inputs() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        // Renders the output for an impulse of the given level in each input
+        let render = |input_matrix: InputMatrix, impulse: [Sample; 2]| {
+            let mut galactic = Galactic::new().with_input_matrix(input_matrix);
+            (galactic.fpdL, galactic.fpdR) = (16386, 16386);
+            galactic.init(sample_rate);
+            let mut output = Vec::new();
+            let mut left_out = vec![0.0; block_size];
+            let mut right_out = vec![0.0; block_size];
+            for block in 0..200 {
+                let (mut left, mut right) = (control(0.0), control(0.0));
+                if block == 0 {
+                    (left[0], right[0]) = (impulse[0], impulse[1]);
+                }
+                galactic.process(
+                    &left,
+                    &right,
+                    &control(0.5),
+                    &control(0.5),
+                    &control(0.5),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                output.extend(left_out.iter().zip(&right_out).map(|(&l, &r)| [l, r]));
+            }
+            output
+        };
+        let energy = |output: &[[Sample; 2]]| output.iter().flatten().map(|s| s * s).sum::<Sample>();
+        let distance = |a: &[[Sample; 2]], b: &[[Sample; 2]]| {
+            let pairs = a.iter().flatten().zip(b.iter().flatten());
+            pairs.map(|(a, b)| (a - b).abs()).fold(0.0, Sample::max)
+        };
+        let left = render(InputMatrix::Straight, [1.0, 0.0]);
+        let right = render(InputMatrix::Straight, [0.0, 1.0]);
+        assert!(distance(&left, &right) > 1e-3);
+        // Swapping the inputs swaps which of the two renders they give
+        assert!(distance(&render(InputMatrix::Swap, [0.0, 1.0]), &left) < 1e-6);
+        assert!(distance(&render(InputMatrix::Swap, [1.0, 0.0]), &right) < 1e-6);
+        // The sum doesn't depend on which side the impulse came from
+        let sum_left = render(InputMatrix::Sum, [1.0, 0.0]);
+        assert!(distance(&sum_left, &render(InputMatrix::Sum, [0.0, 1.0])) < 1e-6);
+        assert!(energy(&sum_left) > 1e-3);
+        // A centred sound doesn't reach the reverb through the difference, a one sided one does
+        let centred = energy(&render(InputMatrix::Straight, [1.0, 1.0]));
+        assert!(energy(&render(InputMatrix::Difference, [1.0, 1.0])) < centred * 1e-6);
+        assert!(energy(&render(InputMatrix::Difference, [1.0, 0.0])) > centred * 0.01);
+    }
+
+    #[test]
+    fn replace_steps_are_smoothed() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        // The left output for a steady sine, with replace stepping from `from` to `to` after 100 blocks
+        let render = |from: Sample, to: Sample| {
+            let mut galactic = Galactic::new();
+            (galactic.fpdL, galactic.fpdR) = (16386, 16386);
+            galactic.init(sample_rate);
+            let mut output = Vec::new();
+            let mut left_out = vec![0.0; block_size];
+            let mut right_out = vec![0.0; block_size];
+            for block in 0..200 {
+                let input: Vec<Sample> = (0..block_size)
+                    .map(|i| {
+                        let phase = (block * block_size + i) as Sample * 50.0 / 44100.0;
+                        (phase * std::f32::consts::TAU).sin() * 0.25
+                    })
+                    .collect();
+                galactic.process(
+                    &input,
+                    &input,
+                    &control(0.5),
+                    &control(if block < 100 { from } else { to }),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                output.extend_from_slice(&left_out);
+            }
+            output
+        };
+        let largest_step = |output: &[Sample]| {
+            output.windows(3).map(|w| (w[2] - 2.0 * w[1] + w[0]).abs()).fold(0.0, Sample::max)
+        };
+        // A step would show up as a spike in the second difference once it comes out of the delays, about 2500 samples after the input
+        let stepped = render(0.9, 0.1);
+        let before = largest_step(&stepped[2560..6400]);
+        let after = largest_step(&stepped[6400..]);
+        assert!(before > 0.0);
+        assert!(after < before * 2.0, "{after} {before}");
+    }
+
+    #[test]
+    fn size_sweeps_do_not_zipper() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        // The left output for a steady sine, with size sweeping from 0.1 to 1 over 4 blocks after 200 blocks
+        let mut galactic = Galactic::new();
+        (galactic.fpdL, galactic.fpdR) = (16386, 16386);
+        galactic.init(sample_rate);
+        let mut output = Vec::new();
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        for block in 0..2000 {
+            let input: Vec<Sample> = (0..block_size)
+                .map(|i| {
+                    let phase = (block * block_size + i) as Sample * 50.0 / 44100.0;
+                    (phase * std::f32::consts::TAU).sin() * 0.25
+                })
+                .collect();
+            let size = 0.1 + 0.9 * ((block as Sample - 200.0) / 4.0).clamp(0.0, 1.0);
+            galactic.process(
+                &input,
+                &input,
+                &control(size),
+                &control(0.5),
+                &control(1.0),
+                &control(0.0),
+                &control(0.0),
+                &control(1.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &mut left_out,
+                &mut right_out,
+                sample_rate,
+            );
+            output.extend_from_slice(&left_out);
+        }
+        let largest_step = |output: &[Sample]| {
+            output.windows(3).map(|w| (w[2] - 2.0 * w[1] + w[0]).abs()).fold(0.0, Sample::max)
+        };
+        // A jump in a delay shows up as a spike in the second difference, which for the steady reverb before the sweep only follows the sine and the noise of the tail
+        let before = largest_step(&output[6400..12800]);
+        let sweep = largest_step(&output[12800..]);
+        assert!(before > 0.0);
+        assert!(sweep < before * 2.0, "{sweep} {before}");
+        assert!(output.iter().all(|s| s.is_finite()));
+    }
+
+    #[test]
+    fn wet_gain_db_only_scales_the_wet_signal() {
+        let sample_rate = SampleRate::from(44100.0);
+        let block_size = 64;
+        let run = |mix: Sample, wet_gain_db: Sample| {
+            let mut galactic = Galactic::new();
+            galactic.init(sample_rate);
+            (galactic.fpdL, galactic.fpdR) = (16386, 16386);
+            let control = |value: Sample| vec![value; block_size];
+            let mut output = Vec::new();
+            let mut left_out = vec![0.0; block_size];
+            let mut right_out = vec![0.0; block_size];
+            for block in 0..200 {
+                let mut input = vec![0.0; block_size];
+                if block == 0 {
+                    input[0] = 0.5;
+                }
+                galactic.process(
+                    &input,
+                    &input,
+                    &control(0.5),
+                    &control(0.5),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(mix),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(wet_gain_db),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                output.extend_from_slice(&left_out);
+            }
+            output
+        };
+        // Fully dry there is nothing for the wet gain to scale
+        assert_eq!(run(0.0, 12.0), run(0.0, 0.0));
+        // Half wet the dry part stays, so each doubling of the wet gain adds twice as much as the one before
+        let double = 20.0 * (2.0 as Sample).log10();
+        let [unity, twice, four_times] = [0.0, double, 2.0 * double].map(|db| run(0.5, db));
+        let peak = |output: &[Sample]| output.iter().fold(0.0, |peak: Sample, s| peak.max(s.abs()));
+        let tolerance = 1e-3 * peak(&four_times[1..]);
+        // The dry impulse is the first sample, before the reverb has put anything out
+        assert_eq!(twice[0], unity[0]);
+        let tail = |output: &[Sample]| peak(&output[1000..]);
+        assert!((tail(&twice) / tail(&unity) - 2.0).abs() < 0.1, "{}", tail(&twice) / tail(&unity));
+        for ((unity, twice), four_times) in unity.iter().zip(&twice).zip(&four_times) {
+            let (first, second) = (twice - unity, four_times - twice);
+            assert!((second - first * 2.0).abs() < tolerance, "{first} {second}");
+        }
+    }
+
+    #[test]
+    fn damping_is_continuous_from_zero() {
+        for brightness in [0.0 as Sample, 0.3, 0.5, 0.9, 1.0] {
+            let lowpass: Sample = (1.00001 - (1.0 - brightness)).powi(2);
+            // Bit identical to the tail filter following brightness
+            assert_eq!(tail_lowpass(lowpass, 0.0).to_bits(), lowpass.to_bits());
+            assert!((tail_lowpass(lowpass, 0.001) - lowpass).abs() < lowpass * 0.01);
+            assert!(tail_lowpass(lowpass, 1.0) < lowpass * 0.02);
+        }
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let mut galactics = [Galactic::new(), Galactic::new()];
+        galactics[1].fpdL = galactics[0].fpdL;
+        galactics[1].fpdR = galactics[0].fpdR;
+        let control = |value: Sample| vec![value; block_size];
+        let mut outputs = [vec![], vec![]];
+        for (galactic, (damping, output)) in
+            galactics.iter_mut().zip([0.0, 0.001].iter().zip(outputs.iter_mut()))
+        {
+            galactic.init(sample_rate);
+            let mut left_out = vec![0.0; block_size];
+            let mut right_out = vec![0.0; block_size];
+            for block in 0..100 {
+                let input = control(if block < 2 { 0.5 } else { 0.0 });
+                galactic.process(
+                    &input,
+                    &input,
+                    &control(1.0),
+                    &control(0.5),
+                    &control(0.5),
+                    &control(*damping),
+                    &control(0.5),
+                    &control(1.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &control(0.0),
+                    &mut left_out,
+                    &mut right_out,
+                    sample_rate,
+                );
+                output.extend_from_slice(&left_out);
+            }
+        }
+        let peak = outputs[0].iter().fold(0.0, |p: Sample, s| p.max(s.abs()));
+        let difference = outputs[0]
+            .iter()
+            .zip(&outputs[1])
+            .fold(0.0, |p: Sample, (a, b)| p.max((a - b).abs()));
+        assert!(peak > 1e-3);
+        assert!(difference < peak * 0.05, "{difference} {peak}");
+    }
+
+    #[test]
+    fn shimmer_decays() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let mut galactic = Galactic::new();
+        galactic.init(sample_rate);
+        let control = |value: Sample| vec![value; block_size];
+        let mut input = vec![0.0; block_size];
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        let mut peaks = Vec::new();
+        for block in 0..44100 * 4 / block_size {
+            input.fill(if block < 10 { 0.5 } else { 0.0 });
+            galactic.process(
+                &input,
+                &input,
+                &control(0.0),
+                &control(1.0),
+                &control(1.0),
+                &control(0.0),
+                &control(0.5),
+                &control(1.0),
+                &control(1.0),
+                &control(12.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &mut left_out,
+                &mut right_out,
+                sample_rate,
+            );
+            peaks.push(left_out.iter().chain(&right_out).fold(0.0, |p: Sample, s| p.max(s.abs())));
+        }
+        let onset = peaks[..100].iter().fold(0.0, |p: Sample, &s| p.max(s));
+        let end = peaks[peaks.len() - 100..].iter().fold(0.0, |p: Sample, &s| p.max(s));
+        assert!(onset > 0.1);
+        assert!(end < onset * 1e-4, "{end} {onset}");
+    }
+    #[test]
+    fn shimmer_stays_bounded_as_the_tail_freezes() {
+        let sample_rate = SampleRate::from(44100.);
+        let block_size = 64;
+        let blocks_per_second = 44100 / block_size;
+        let mut galactic = Galactic::new();
+        galactic.init(sample_rate);
+        (galactic.fpdL, galactic.fpdR) = (16386, 16386);
+        let control = |value: Sample| vec![value; block_size];
+        let mut input = vec![0.0; block_size];
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        let mut peaks = Vec::new();
+        // A shimmering tail is built up for a second and then frozen with the shimmer still fully up
+        for block in 0..blocks_per_second * 10 {
+            input.fill(if block < 10 { 0.5 } else { 0.0 });
+            let replace = if block < blocks_per_second { 0.3 } else { 0.0 };
+            galactic.process(
+                &input,
+                &input,
+                &control(1.0),
+                &control(replace),
+                &control(1.0),
+                &control(0.0),
+                &control(0.5),
+                &control(1.0),
+                &control(1.0),
+                &control(12.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &control(0.0),
+                &mut left_out,
+                &mut right_out,
+                sample_rate,
+            );
+            peaks.push(left_out.iter().chain(&right_out).fold(0.0, |p: Sample, s| p.max(s.abs())));
+        }
+        let seconds: Vec<Sample> = peaks
+            .chunks(blocks_per_second)
+            .map(|second| second.iter().fold(0.0, |p: Sample, &s| p.max(s)))
+            .collect();
+        // The frozen tail holds its level without the shimmer making it grow, the modulation moves the peaks by about 20%
+        let frozen = seconds[2..5].iter().fold(0.0, |p: Sample, &s| p.max(s));
+        assert!(frozen > 1e-3);
+        for second in &seconds[5..] {
+            assert!(*second < frozen * 1.2 && *second > frozen * 0.5, "{seconds:?}");
+        }
+    }
+
 }
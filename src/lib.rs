@@ -1,3 +1,8 @@
 mod luffverb;
+pub mod analysis;
 pub mod galactic;
+pub mod params;
+pub mod plate;
+pub mod reverb;
+pub mod schroeder;
 pub use luffverb::*;
\ No newline at end of file
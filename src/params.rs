@@ -0,0 +1,44 @@
+//! Parameter metadata for building user interfaces.
+
+use knyst::Sample;
+
+/// The unit of a parameter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamUnit {
+    Hz,
+    Milliseconds,
+    Seconds,
+    Semitones,
+    Decibels,
+    /// A unitless value, e.g. 0-1 for an amount
+    Ratio,
+}
+
+/// Description of one input parameter of a reverb. `name` is the name of the input on the Gen, i.e. of the setter on its handle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParamInfo {
+    pub name: &'static str,
+    pub min: Sample,
+    pub max: Sample,
+    /// Recommended starting value for a UI. An input that isn't connected reads 0 rather than this value, see the `param_metadata` of each reverb for what 0 does.
+    pub default: Sample,
+    pub unit: ParamUnit,
+}
+
+impl ParamInfo {
+    pub const fn new(
+        name: &'static str,
+        min: Sample,
+        max: Sample,
+        default: Sample,
+        unit: ParamUnit,
+    ) -> Self {
+        Self {
+            name,
+            min,
+            max,
+            default,
+            unit,
+        }
+    }
+}
@@ -0,0 +1,480 @@
+//! Plate reverb
+//!
+//! A figure eight tank after Jon Dattorro, "Effect Design Part 1: Reverberator and Other Filters", J. Audio Eng. Soc. 45(9), 1997. The input is smeared by a chain of allpasses and fed into two halves of a tank, each of which feeds the other. The output is a sum of taps spread through the tank, which gives the dense and bright onset of a plate.
+
+use knyst::gen::delay::StaticSampleDelay;
+use knyst::gen::filter::one_pole::OnePoleLpf;
+use knyst::gen::GenState;
+use knyst::prelude::impl_gen;
+use knyst::{Sample, SampleRate};
+
+use crate::params::{ParamInfo, ParamUnit};
+use crate::reverb::{clear_delay, predelay, Allpass, ControlBuffers, Reverb};
+
+/// The delay lengths of the paper are in samples at this rate
+const PLATE_SAMPLE_RATE: Sample = 29761.0;
+/// Lengths and gains of the allpasses diffusing the input
+const INPUT_DIFFUSER_LENGTHS: [usize; 4] = [142, 107, 379, 277];
+const INPUT_DIFFUSER_GAINS: [Sample; 4] = [0.75, 0.75, 0.625, 0.625];
+/// Lengths of the modulated allpass, the first delay, the second allpass and the second delay of each half of the tank
+const TANK_LENGTHS: [[usize; 4]; 2] = [[672, 4453, 1800, 3720], [908, 4217, 2656, 3163]];
+/// Gain of the modulated allpass in the tank, negative as in the paper
+const DECAY_DIFFUSION_1: Sample = -0.7;
+/// How far the modulated allpasses move in samples and how fast
+const MODULATION_EXCURSION: Sample = 16.0;
+const MODULATION_FREQ: Sample = 1.0;
+/// Taps of the left and right output as (half of the tank, stage, samples ago, sign)
+const OUTPUT_TAPS: [[(usize, usize, usize, Sample); 7]; 2] = [
+    [
+        (1, 1, 266, 1.0),
+        (1, 1, 2974, 1.0),
+        (1, 2, 1913, -1.0),
+        (1, 3, 1996, 1.0),
+        (0, 1, 1990, -1.0),
+        (0, 2, 187, -1.0),
+        (0, 3, 1066, -1.0),
+    ],
+    [
+        (0, 1, 353, 1.0),
+        (0, 1, 3627, 1.0),
+        (0, 2, 1228, -1.0),
+        (0, 3, 2673, 1.0),
+        (1, 1, 2111, -1.0),
+        (1, 2, 335, -1.0),
+        (1, 3, 121, -1.0),
+    ],
+];
+const OUTPUT_GAIN: Sample = 0.6;
+/// Highest decay, the gain of each half of the tank. The allpasses don't change the level and the damping only lowers it, so a decay below 1 keeps the loop gain of the tank below 1.
+pub const MAX_DECAY: Sample = 0.99;
+/// Cutoff of the damping lowpass in the tank at a damping of 1. It goes up exponentially to [`MAX_DAMPING_FREQ`] towards 0 and is off at 0.
+pub const MIN_DAMPING_FREQ: Sample = 500.0;
+pub const MAX_DAMPING_FREQ: Sample = 20000.0;
+/// Longest predelay
+pub const MAX_PREDELAY_MS: Sample = 500.0;
+/// Time constant of the smoothing of the predelay and the mix
+const SMOOTHING_SECONDS: Sample = 0.05;
+const MIX_SMOOTHING_SECONDS: Sample = 0.005;
+
+/// The sample written `samples_ago` writes ago to a delay with a buffer of `length` samples
+#[inline]
+fn tap(delay: &mut StaticSampleDelay, length: usize, samples_ago: usize) -> Sample {
+    delay.read_at((delay.position + length - samples_ago.clamp(1, length)) % length)
+}
+
+/// One half of the figure eight tank
+struct TankHalf {
+    modulated: Allpass,
+    delay_1: StaticSampleDelay,
+    damping: OnePoleLpf,
+    allpass: Allpass,
+    delay_2: StaticSampleDelay,
+    /// Lengths of the stages at the current sample rate, the first one without the modulation
+    lengths: [usize; 4],
+}
+
+impl TankHalf {
+    /// `lengths` at the current sample rate, which is `scale` times the rate of the paper
+    fn new(lengths: [usize; 4], scale: Sample) -> Self {
+        Self {
+            modulated: Allpass::new(lengths[0] + (MODULATION_EXCURSION * scale) as usize + 2),
+            delay_1: StaticSampleDelay::new(lengths[1].max(1)),
+            damping: OnePoleLpf::new(),
+            allpass: Allpass::new(lengths[2]),
+            delay_2: StaticSampleDelay::new(lengths[3].max(1)),
+            lengths,
+        }
+    }
+    /// Output of `stage`, 1 for the first delay, 2 for the second allpass and 3 for the second delay, `samples_ago` at the rate of the paper
+    fn tap(&mut self, stage: usize, samples_ago: usize, scale: Sample) -> Sample {
+        let samples_ago = (samples_ago as Sample * scale) as usize;
+        match stage {
+            1 => tap(&mut self.delay_1, self.lengths[1].max(1), samples_ago),
+            2 => tap(&mut self.allpass.delay, self.allpass.length, samples_ago),
+            _ => tap(&mut self.delay_2, self.lengths[3].max(1), samples_ago),
+        }
+    }
+    /// Process one sample and return the output of the half, which feeds the other half
+    #[inline]
+    fn process(
+        &mut self,
+        input: Sample,
+        modulation: Sample,
+        decay: Sample,
+        decay_diffusion_2: Sample,
+        damping: bool,
+    ) -> Sample {
+        let delay_samples = self.lengths[0] as Sample + 1.0 + modulation;
+        let sig = self.modulated.process_modulated(input, DECAY_DIFFUSION_1, delay_samples);
+        let delayed = self.delay_1.read();
+        self.delay_1.write_and_advance(sig);
+        // The lowpass keeps running while the damping is off so that turning it on doesn't click
+        let lowpassed = self.damping.op.process_lp(delayed as f64) as Sample;
+        let damped = if damping { lowpassed } else { delayed };
+        let sig = self.allpass.process(damped * decay, decay_diffusion_2);
+        let delayed = self.delay_2.read();
+        self.delay_2.write_and_advance(sig);
+        delayed * decay
+    }
+    fn clear(&mut self) {
+        self.modulated.clear();
+        clear_delay(&mut self.delay_1, self.lengths[1].max(1));
+        self.damping.op.reset();
+        self.allpass.clear();
+        clear_delay(&mut self.delay_2, self.lengths[3].max(1));
+    }
+}
+
+/// A plate reverb with a Dattorro style figure eight tank, upload it with `plate`. The left and right inputs are summed into the tank and the output is stereo, from different taps of the tank. The modulated allpasses in the tank keep it from ringing metallically.
+///
+/// Compared to [`crate::LuffVerb`] and [`crate::galactic::Galactic`] it is bright and dense from the first milliseconds and doesn't have a size, the decay sets how long it rings.
+pub struct Plate {
+    predelay: StaticSampleDelay,
+    predelay_length: usize,
+    /// Smoothed predelay time in samples and mix, NaN until the first sample
+    predelay_smoothed: Sample,
+    mix_smoothed: Sample,
+    input_diffusers: [Allpass; 4],
+    tank: [TankHalf; 2],
+    /// Output of each half of the tank from the last sample, which feeds the other half
+    feedback: [Sample; 2],
+    /// Phase of the modulation of the tank, 0-1
+    modulation_phase: Sample,
+    /// The damping the lowpasses are set to
+    damping_last: Sample,
+    /// Delay lengths at the current sample rate relative to the paper
+    scale: Sample,
+    sample_rate: Sample,
+    /// State for the [`Reverb`] interface
+    reverb_mix: Sample,
+    reverb_size: Sample,
+    reverb_controls: ControlBuffers,
+}
+
+impl Plate {
+    /// Number of output channels of the node, left and right
+    pub const OUTPUT_CHANNELS: usize = 2;
+    /// Name, range, default and unit of every control input, in the order of the inputs. The audio inputs are not included.
+    ///
+    /// Inputs that aren't connected read 0: a `decay` of 0 keeps the tank from recirculating, which leaves a short splash, a `damping` of 0 turns the damping off, a `predelay_ms` of 0 has no predelay and a `mix` of 0 is fully dry.
+    pub fn param_metadata() -> &'static [ParamInfo] {
+        const PARAMS: [ParamInfo; 4] = [
+            ParamInfo::new("decay", 0.0, MAX_DECAY, 0.5, ParamUnit::Ratio),
+            ParamInfo::new("damping", 0.0, 1.0, 0.2, ParamUnit::Ratio),
+            ParamInfo::new("predelay_ms", 0.0, MAX_PREDELAY_MS, 0.0, ParamUnit::Milliseconds),
+            ParamInfo::new("mix", 0.0, 1.0, 0.3, ParamUnit::Ratio),
+        ];
+        &PARAMS
+    }
+    fn scaled(length: usize, scale: Sample) -> usize {
+        ((length as Sample * scale) as usize).max(1)
+    }
+}
+
+impl Default for Plate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[impl_gen]
+impl Plate {
+    pub fn new() -> Self {
+        Self {
+            predelay: StaticSampleDelay::new(1),
+            predelay_length: 1,
+            predelay_smoothed: Sample::NAN,
+            mix_smoothed: Sample::NAN,
+            input_diffusers: INPUT_DIFFUSER_LENGTHS.map(Allpass::new),
+            tank: TANK_LENGTHS.map(|lengths| TankHalf::new(lengths, 1.0)),
+            feedback: [0.0; 2],
+            modulation_phase: 0.0,
+            damping_last: Sample::NAN,
+            scale: 1.0,
+            sample_rate: PLATE_SAMPLE_RATE,
+            reverb_mix: 0.3,
+            reverb_size: 0.5,
+            reverb_controls: ControlBuffers::default(),
+        }
+    }
+    /// Allocate the delays for the sample rate. Not real time safe.
+    pub fn init(&mut self, sample_rate: SampleRate) {
+        self.sample_rate = *sample_rate;
+        self.scale = *sample_rate / PLATE_SAMPLE_RATE;
+        let scale = self.scale;
+        self.input_diffusers =
+            INPUT_DIFFUSER_LENGTHS.map(|length| Allpass::new(Self::scaled(length, scale)));
+        self.tank = TANK_LENGTHS
+            .map(|lengths| TankHalf::new(lengths.map(|length| Self::scaled(length, scale)), scale));
+        // One extra sample so that the longest predelay can be read after the input is written
+        self.predelay_length = (MAX_PREDELAY_MS * 0.001 * *sample_rate) as usize + 2;
+        self.predelay = StaticSampleDelay::new(self.predelay_length);
+        self.predelay_smoothed = Sample::NAN;
+        self.feedback = [0.0; 2];
+        self.damping_last = Sample::NAN;
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn process(
+        &mut self,
+        left: &[Sample],
+        right: &[Sample],
+        decay: &[Sample],
+        damping: &[Sample],
+        predelay_ms: &[Sample],
+        mix: &[Sample],
+        left_out: &mut [Sample],
+        right_out: &mut [Sample],
+        sample_rate: SampleRate,
+    ) -> GenState {
+        let predelay_coeff = 1.0 - (-1.0 / (SMOOTHING_SECONDS * *sample_rate)).exp();
+        let mix_coeff = 1.0 - (-1.0 / (MIX_SMOOTHING_SECONDS * *sample_rate)).exp();
+        let predelay_max = (self.predelay_length - 2) as Sample;
+        let predelay_samples = |ms: Sample| (ms * 0.001 * *sample_rate).clamp(0.0, predelay_max);
+        if self.predelay_smoothed.is_nan() {
+            self.predelay_smoothed = predelay_samples(predelay_ms[0]);
+        }
+        if self.mix_smoothed.is_nan() {
+            self.mix_smoothed = mix[0].clamp(0.0, 1.0);
+        }
+        let modulation_step = MODULATION_FREQ / *sample_rate;
+        let excursion = MODULATION_EXCURSION * self.scale;
+        for (f, (out_l, out_r)) in left_out.iter_mut().zip(right_out.iter_mut()).enumerate() {
+            let decay = decay[f].clamp(0.0, MAX_DECAY);
+            // As in the paper, the second allpass diffuses less when the tank decays fast
+            let decay_diffusion_2 = (decay + 0.15).clamp(0.25, 0.5);
+            let damping = damping[f].clamp(0.0, 1.0);
+            let damped = damping > 0.0;
+            if damped && damping != self.damping_last {
+                self.damping_last = damping;
+                let freq = MAX_DAMPING_FREQ * (MIN_DAMPING_FREQ / MAX_DAMPING_FREQ).powf(damping);
+                for half in &mut self.tank {
+                    half.damping.op.set_freq_lowpass(freq as f64, *sample_rate as f64);
+                }
+            }
+            self.mix_smoothed += (mix[f].clamp(0.0, 1.0) - self.mix_smoothed) * mix_coeff;
+
+            let dry_l = left[f];
+            let dry_r = right[f];
+            self.predelay_smoothed +=
+                (predelay_samples(predelay_ms[f]) - self.predelay_smoothed) * predelay_coeff;
+            let length = self.predelay_length;
+            let mut sig =
+                predelay(&mut self.predelay, length, (dry_l + dry_r) * 0.5, self.predelay_smoothed);
+            for (diffuser, gain) in self.input_diffusers.iter_mut().zip(INPUT_DIFFUSER_GAINS) {
+                sig = diffuser.process(sig, gain);
+            }
+
+            self.modulation_phase = (self.modulation_phase + modulation_step).fract();
+            let modulation = (self.modulation_phase * std::f32::consts::TAU).sin() * excursion;
+            // The two halves are modulated in opposite directions
+            let [feedback_l, feedback_r] = self.feedback;
+            let [left_half, right_half] = &mut self.tank;
+            self.feedback = [
+                left_half.process(sig + feedback_r, modulation, decay, decay_diffusion_2, damped),
+                right_half.process(sig + feedback_l, -modulation, decay, decay_diffusion_2, damped),
+            ];
+
+            let mut wet = [0.0; 2];
+            for (wet, taps) in wet.iter_mut().zip(&OUTPUT_TAPS) {
+                for &(half, stage, samples_ago, sign) in taps {
+                    *wet += self.tank[half].tap(stage, samples_ago, self.scale) * sign;
+                }
+                *wet *= OUTPUT_GAIN;
+            }
+            let mix = self.mix_smoothed;
+            *out_l = dry_l + (wet[0] - dry_l) * mix;
+            *out_r = dry_r + (wet[1] - dry_r) * mix;
+        }
+        GenState::Continue
+    }
+}
+
+/// `size` sets the `decay`, from 0 at 0 to [`MAX_DECAY`] at 1, since a plate has no room size. All the other parameters are at their defaults from [`Plate::param_metadata`].
+impl Reverb for Plate {
+    fn set_mix(&mut self, mix: Sample) {
+        self.reverb_mix = mix.clamp(0.0, 1.0);
+    }
+    fn set_size(&mut self, size: Sample) {
+        self.reverb_size = size.clamp(0.0, 1.0);
+    }
+    fn reset(&mut self) {
+        for diffuser in &mut self.input_diffusers {
+            diffuser.clear();
+        }
+        for half in &mut self.tank {
+            half.clear();
+        }
+        self.feedback = [0.0; 2];
+        clear_delay(&mut self.predelay, self.predelay_length);
+        self.predelay_smoothed = Sample::NAN;
+        self.mix_smoothed = Sample::NAN;
+    }
+    fn process_stereo(
+        &mut self,
+        left: &[Sample],
+        right: &[Sample],
+        left_out: &mut [Sample],
+        right_out: &mut [Sample],
+        sample_rate: SampleRate,
+    ) {
+        let params = Plate::param_metadata();
+        self.reverb_controls.prepare(params, left.len());
+        self.reverb_controls.set(0, self.reverb_size * MAX_DECAY);
+        self.reverb_controls.set(3, self.reverb_mix);
+        let controls = std::mem::take(&mut self.reverb_controls);
+        self.process(
+            left,
+            right,
+            controls.get(0),
+            controls.get(1),
+            controls.get(2),
+            controls.get(3),
+            left_out,
+            right_out,
+            sample_rate,
+        );
+        self.reverb_controls = controls;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the left and right response of a plate to an impulse
+    fn impulse_response(
+        plate: &mut Plate,
+        decay: Sample,
+        damping: Sample,
+        predelay_ms: Sample,
+        mix: Sample,
+        blocks: usize,
+    ) -> [Vec<Sample>; 2] {
+        let sample_rate = SampleRate::from(48000.);
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        let mut outputs = [Vec::new(), Vec::new()];
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        for block in 0..blocks {
+            let mut input = control(0.0);
+            input[0] = if block == 0 { 1.0 } else { 0.0 };
+            plate.process(
+                &input,
+                &input,
+                &control(decay),
+                &control(damping),
+                &control(predelay_ms),
+                &control(mix),
+                &mut left_out,
+                &mut right_out,
+                sample_rate,
+            );
+            outputs[0].extend_from_slice(&left_out);
+            outputs[1].extend_from_slice(&right_out);
+        }
+        outputs
+    }
+
+    fn new_plate() -> Plate {
+        let mut plate = Plate::new();
+        plate.init(SampleRate::from(48000.));
+        plate
+    }
+
+    fn energy(samples: &[Sample]) -> Sample {
+        samples.iter().map(|s| s * s).sum()
+    }
+
+    #[test]
+    fn param_metadata_matches_inputs() {
+        use knyst::gen::Gen;
+        let plate = Plate::new();
+        let inputs: Vec<_> = (0..plate.num_inputs()).map(|i| plate.input_desc(i)).collect();
+        let names: Vec<_> = Plate::param_metadata().iter().map(|p| p.name).collect();
+        assert_eq!(&inputs[2..], &names);
+        for param in Plate::param_metadata() {
+            assert!(param.min <= param.default && param.default <= param.max);
+        }
+    }
+
+    #[test]
+    fn output_channels_match_the_gen() {
+        use knyst::gen::Gen;
+        assert_eq!(Plate::new().num_outputs(), Plate::OUTPUT_CHANNELS);
+    }
+
+    #[test]
+    fn decay_sets_the_length_of_the_tail() {
+        // Energy of the left output between 1 and 2 seconds and between 9 and 10 seconds
+        let tail = |decay: Sample| {
+            let [left, _] = impulse_response(&mut new_plate(), decay, 0.0, 0.0, 1.0, 7500);
+            (energy(&left[48000..96000]), energy(&left[432000..480000]))
+        };
+        let (short, _) = tail(0.5);
+        let (long, _) = tail(0.9);
+        assert!(long > short * 100.0);
+        // Even at the highest decay the tank loses energy on every pass, about 4% per half of the tank, which takes 0.35 s
+        let (early, late) = tail(MAX_DECAY);
+        assert!(late < early * 0.7, "{late} {early}");
+        assert!(late > 0.0);
+        // Without any decay nothing goes around the tank, only the allpasses ring on briefly
+        let [left, _] = impulse_response(&mut new_plate(), 0.0, 0.0, 0.0, 1.0, 750);
+        assert!(energy(&left[24000..]) < energy(&left) * 1e-4);
+    }
+
+    #[test]
+    fn outputs_are_decorrelated() {
+        let [left, right] = impulse_response(&mut new_plate(), 0.7, 0.2, 0.0, 1.0, 750);
+        let cross: Sample = left.iter().zip(&right).map(|(l, r)| l * r).sum();
+        let correlation = cross / (energy(&left) * energy(&right)).sqrt();
+        assert!(correlation.abs() < 0.3, "{correlation}");
+    }
+
+    #[test]
+    fn mix_of_zero_is_dry() {
+        let [left, right] = impulse_response(&mut new_plate(), 0.7, 0.2, 0.0, 0.0, 100);
+        assert_eq!(left[0], 1.0);
+        assert_eq!(right[0], 1.0);
+        assert!(left[1..].iter().chain(&right[1..]).all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn damping_darkens_the_tail() {
+        // Energy of the first difference relative to the energy, which grows with the high frequency content
+        let brightness = |damping: Sample| {
+            let [left, _] = impulse_response(&mut new_plate(), 0.7, damping, 0.0, 1.0, 750);
+            let tail = &left[9600..];
+            let difference: Vec<Sample> = tail.windows(2).map(|w| w[1] - w[0]).collect();
+            energy(&difference) / energy(tail)
+        };
+        let bright = brightness(0.0);
+        let dark = brightness(1.0);
+        assert!(dark < bright * 0.4);
+        assert!(brightness(0.5) < bright);
+        assert!(dark < brightness(0.5));
+    }
+
+    #[test]
+    fn predelay_delays_the_onset() {
+        let onset = |predelay_ms: Sample| {
+            let [left, _] = impulse_response(&mut new_plate(), 0.7, 0.0, predelay_ms, 1.0, 200);
+            left.iter().position(|s| s.abs() > 1e-6).unwrap()
+        };
+        let direct = onset(0.0);
+        // 100 ms at 48 kHz
+        assert_eq!(onset(100.0) - direct, 4800);
+    }
+
+    #[test]
+    fn reset_silences_the_plate() {
+        let mut plate = new_plate();
+        impulse_response(&mut plate, 0.9, 0.0, 0.0, 1.0, 100);
+        Reverb::reset(&mut plate);
+        let mut left_out = vec![1.0; 64];
+        let mut right_out = vec![1.0; 64];
+        let sample_rate = SampleRate::from(48000.);
+        plate.process_stereo(&[0.0; 64], &[0.0; 64], &mut left_out, &mut right_out, sample_rate);
+        assert!(left_out.iter().chain(&right_out).all(|&s| s == 0.0));
+    }
+}
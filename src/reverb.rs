@@ -0,0 +1,109 @@
+//! A common interface for the reverbs in this crate.
+
+use knyst::{prelude::delay::StaticSampleDelay, Sample, SampleRate};
+
+use crate::params::ParamInfo;
+
+/// Common controls of the reverbs in this crate so that they can be swapped for each other. Each reverb maps its own parameters to these, see the implementations for the details.
+///
+/// The parameters which aren't part of the interface are left at the defaults from the `param_metadata` of each reverb.
+pub trait Reverb {
+    /// Dry/wet balance, 0 is dry and 1 is wet
+    fn set_mix(&mut self, mix: Sample);
+    /// Size of the room, 0-1
+    fn set_size(&mut self, size: Sample);
+    /// Clear all internal state so that the reverb is silent. Real time safe, but costs about as much as processing as many samples as the longest delay.
+    fn reset(&mut self);
+    /// Process one block of stereo audio. The reverb has to be initialised first and all slices have to be the same length. Allocates when it is called with a new block size.
+    fn process_stereo(
+        &mut self,
+        left: &[Sample],
+        right: &[Sample],
+        left_out: &mut [Sample],
+        right_out: &mut [Sample],
+        sample_rate: SampleRate,
+    );
+}
+
+/// Constant control inputs for calling a `process` function directly.
+#[derive(Default)]
+pub(crate) struct ControlBuffers {
+    buffers: Vec<Vec<Sample>>,
+}
+impl ControlBuffers {
+    /// Fill one buffer per parameter with its default. Only allocates when the number of parameters or the block size changes.
+    pub(crate) fn prepare(&mut self, params: &[ParamInfo], block_size: usize) {
+        if self.buffers.len() != params.len()
+            || self.buffers.first().map(Vec::len) != Some(block_size)
+        {
+            self.buffers = vec![vec![0.0; block_size]; params.len()];
+        }
+        for (buffer, param) in self.buffers.iter_mut().zip(params) {
+            buffer.fill(param.default);
+        }
+    }
+    pub(crate) fn set(&mut self, index: usize, value: Sample) {
+        self.buffers[index].fill(value);
+    }
+    pub(crate) fn get(&self, index: usize) -> &[Sample] {
+        &self.buffers[index]
+    }
+}
+
+/// Fill the first `buffer_length` samples of a delay with zeros. The delay is left at a delay length of `buffer_length` with its position where it started.
+pub(crate) fn clear_delay(delay: &mut StaticSampleDelay, buffer_length: usize) {
+    delay.set_delay_length(buffer_length);
+    for _ in 0..buffer_length {
+        delay.write_and_advance(0.0);
+    }
+}
+
+/// Write `input` into a predelay with a buffer of `length` samples and read it back `delay` samples later. The read is interpolated so that the delay time can glide, and a delay of 0 returns `input` unchanged.
+pub(crate) fn predelay(
+    delay: &mut StaticSampleDelay,
+    length: usize,
+    input: Sample,
+    delay_samples: Sample,
+) -> Sample {
+    delay.write_and_advance(input);
+    // The sample just written is right before the write position
+    let index = (delay.position + length - 1) as Sample - delay_samples;
+    delay.read_at_lin(index)
+}
+
+/// An allpass on a [`StaticSampleDelay`], optionally read at a modulated position
+pub(crate) struct Allpass {
+    pub(crate) delay: StaticSampleDelay,
+    /// Length of the buffer of the delay
+    pub(crate) length: usize,
+}
+
+impl Allpass {
+    pub(crate) fn new(length: usize) -> Self {
+        Self {
+            delay: StaticSampleDelay::new(length.max(1)),
+            length: length.max(1),
+        }
+    }
+    /// Process one sample with the full length of the delay
+    #[inline]
+    pub(crate) fn process(&mut self, input: Sample, gain: Sample) -> Sample {
+        let delayed = self.delay.read();
+        let v = input - gain * delayed;
+        self.delay.write_and_advance(v);
+        delayed + gain * v
+    }
+    /// Process one sample with a delay of `delay_samples`, which can be fractional but has to be between 1 and the length of the buffer
+    #[inline]
+    pub(crate) fn process_modulated(&mut self, input: Sample, gain: Sample, delay_samples: Sample) -> Sample {
+        // The position is the oldest sample, one write further than the newest
+        let index = (self.delay.position + self.length) as Sample - delay_samples;
+        let delayed = self.delay.read_at_lin(index);
+        let v = input - gain * delayed;
+        self.delay.write_and_advance(v);
+        delayed + gain * v
+    }
+    pub(crate) fn clear(&mut self) {
+        clear_delay(&mut self.delay, self.length);
+    }
+}
@@ -0,0 +1,375 @@
+//! Schroeder reverb
+//!
+//! The classic digital reverb after Manfred Schroeder, "Natural Sounding Artificial Reverberation", J. Audio Eng. Soc. 10(3), 1962: parallel feedback combs build up the decay and a series of allpasses thickens the echoes. As suggested by James A. Moorer, "About This Reverberation Business", Computer Music Journal 3(2), 1979, each comb has a lowpass in its feedback so that the highs die away faster.
+
+use knyst::gen::delay::StaticSampleDelay;
+use knyst::gen::filter::one_pole::OnePoleLpf;
+use knyst::gen::GenState;
+use knyst::prelude::impl_gen;
+use knyst::{Sample, SampleRate};
+
+use crate::params::{ParamInfo, ParamUnit};
+use crate::reverb::{clear_delay, Allpass, ControlBuffers, Reverb};
+
+/// Lengths of the combs from Schroeder's paper. They are far enough from any common multiple that their echoes don't pile up.
+const COMB_LENGTHS_MS: [Sample; 4] = [29.7, 37.1, 41.1, 43.7];
+/// Lengths and gain of the allpasses from Schroeder's paper
+const ALLPASS_LENGTHS_MS: [Sample; 2] = [5.0, 1.7];
+const ALLPASS_GAIN: Sample = 0.7;
+/// The right output sums the combs with these signs, which decorrelates it from the left output without any more delays
+const RIGHT_COMB_SIGNS: [Sample; 4] = [1.0, -1.0, 1.0, -1.0];
+/// Scales the sum of the combs down to about the level of the input
+const OUTPUT_GAIN: Sample = 0.25;
+/// Longest decay
+pub const MAX_DECAY_SECONDS: Sample = 10.0;
+/// Cutoff of the damping lowpass in the combs at a damping of 1. It goes up exponentially to [`MAX_DAMPING_FREQ`] towards 0 and is off at 0.
+pub const MIN_DAMPING_FREQ: Sample = 500.0;
+pub const MAX_DAMPING_FREQ: Sample = 20000.0;
+/// Time constant of the smoothing of the mix
+const MIX_SMOOTHING_SECONDS: Sample = 0.005;
+
+/// A feedback comb with a lowpass in the feedback
+struct Comb {
+    delay: StaticSampleDelay,
+    length: usize,
+    damping: OnePoleLpf,
+}
+
+impl Comb {
+    fn new(length: usize) -> Self {
+        Self {
+            delay: StaticSampleDelay::new(length.max(1)),
+            length: length.max(1),
+            damping: OnePoleLpf::new(),
+        }
+    }
+    /// Process one sample, feeding the output back with `gain`
+    #[inline]
+    fn process(&mut self, input: Sample, gain: Sample, damping: bool) -> Sample {
+        let delayed = self.delay.read();
+        // The lowpass keeps running while the damping is off so that turning it on doesn't click
+        let lowpassed = self.damping.op.process_lp(delayed as f64) as Sample;
+        let feedback = if damping { lowpassed } else { delayed };
+        self.delay.write_and_advance(input + feedback * gain);
+        delayed
+    }
+    fn clear(&mut self) {
+        clear_delay(&mut self.delay, self.length);
+        self.damping.op.reset();
+    }
+}
+
+/// A Schroeder reverb with four parallel combs into two allpasses per output, upload it with `schroeder`. The left and right inputs are summed and the output is stereo, the right output combines the combs differently.
+///
+/// It is much cheaper than [`crate::LuffVerb`], [`crate::galactic::Galactic`] and [`crate::plate::Plate`], and sounds like it: the echoes stay sparse and the tail has the fluttery, metallic sound of early digital reverbs.
+pub struct Schroeder {
+    combs: [Comb; 4],
+    allpasses: [[Allpass; 2]; 2],
+    /// Decay the comb gains are set for and the gains, in the order of [`COMB_LENGTHS_MS`]
+    decay_last: Sample,
+    comb_gains: [Sample; 4],
+    /// The damping the lowpasses are set to
+    damping_last: Sample,
+    /// Smoothed mix, NaN until the first sample
+    mix_smoothed: Sample,
+    sample_rate: Sample,
+    /// State for the [`Reverb`] interface
+    reverb_mix: Sample,
+    reverb_size: Sample,
+    reverb_controls: ControlBuffers,
+}
+
+impl Schroeder {
+    /// Number of output channels of the node, left and right
+    pub const OUTPUT_CHANNELS: usize = 2;
+    /// Name, range, default and unit of every control input, in the order of the inputs. The audio inputs are not included.
+    ///
+    /// Inputs that aren't connected read 0: a `decay_seconds` of 0 keeps the combs from recirculating, which leaves a single echo from each, a `damping` of 0 turns the damping off and a `mix` of 0 is fully dry.
+    pub fn param_metadata() -> &'static [ParamInfo] {
+        const PARAMS: [ParamInfo; 3] = [
+            ParamInfo::new("decay_seconds", 0.0, MAX_DECAY_SECONDS, 1.5, ParamUnit::Seconds),
+            ParamInfo::new("damping", 0.0, 1.0, 0.2, ParamUnit::Ratio),
+            ParamInfo::new("mix", 0.0, 1.0, 0.3, ParamUnit::Ratio),
+        ];
+        &PARAMS
+    }
+    fn length(ms: Sample, sample_rate: Sample) -> usize {
+        ((ms * 0.001 * sample_rate) as usize).max(1)
+    }
+    /// Feedback gain of a comb of `length` samples for the level to fall by 60 dB in `decay` seconds. It is below 1 for any finite decay, so the combs can't grow.
+    fn comb_gain(length: usize, decay: Sample, sample_rate: Sample) -> Sample {
+        if decay <= 0.0 {
+            return 0.0;
+        }
+        Sample::powf(10.0, -3.0 * length as Sample / (decay * sample_rate))
+    }
+}
+
+impl Default for Schroeder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[impl_gen]
+impl Schroeder {
+    pub fn new() -> Self {
+        Self {
+            combs: [0; 4].map(|_| Comb::new(1)),
+            allpasses: [0; 2].map(|_| [0; 2].map(|_| Allpass::new(1))),
+            decay_last: Sample::NAN,
+            comb_gains: [0.0; 4],
+            damping_last: Sample::NAN,
+            mix_smoothed: Sample::NAN,
+            sample_rate: 48000.0,
+            reverb_mix: 0.3,
+            reverb_size: 0.15,
+            reverb_controls: ControlBuffers::default(),
+        }
+    }
+    /// Allocate the delays for the sample rate. Not real time safe.
+    pub fn init(&mut self, sample_rate: SampleRate) {
+        self.sample_rate = *sample_rate;
+        self.combs = COMB_LENGTHS_MS.map(|ms| Comb::new(Self::length(ms, *sample_rate)));
+        self.allpasses = [0; 2]
+            .map(|_| ALLPASS_LENGTHS_MS.map(|ms| Allpass::new(Self::length(ms, *sample_rate))));
+        self.decay_last = Sample::NAN;
+        self.damping_last = Sample::NAN;
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub fn process(
+        &mut self,
+        left: &[Sample],
+        right: &[Sample],
+        decay_seconds: &[Sample],
+        damping: &[Sample],
+        mix: &[Sample],
+        left_out: &mut [Sample],
+        right_out: &mut [Sample],
+        sample_rate: SampleRate,
+    ) -> GenState {
+        let mix_coeff = 1.0 - (-1.0 / (MIX_SMOOTHING_SECONDS * *sample_rate)).exp();
+        if self.mix_smoothed.is_nan() {
+            self.mix_smoothed = mix[0].clamp(0.0, 1.0);
+        }
+        for (f, (out_l, out_r)) in left_out.iter_mut().zip(right_out.iter_mut()).enumerate() {
+            let decay = decay_seconds[f].clamp(0.0, MAX_DECAY_SECONDS);
+            if decay != self.decay_last {
+                self.decay_last = decay;
+                for (gain, comb) in self.comb_gains.iter_mut().zip(&self.combs) {
+                    *gain = Self::comb_gain(comb.length, decay, *sample_rate);
+                }
+            }
+            let damping = damping[f].clamp(0.0, 1.0);
+            let damped = damping > 0.0;
+            if damped && damping != self.damping_last {
+                self.damping_last = damping;
+                let freq = MAX_DAMPING_FREQ * (MIN_DAMPING_FREQ / MAX_DAMPING_FREQ).powf(damping);
+                for comb in &mut self.combs {
+                    comb.damping.op.set_freq_lowpass(freq as f64, *sample_rate as f64);
+                }
+            }
+            self.mix_smoothed += (mix[f].clamp(0.0, 1.0) - self.mix_smoothed) * mix_coeff;
+
+            let dry_l = left[f];
+            let dry_r = right[f];
+            let input = (dry_l + dry_r) * 0.5;
+            let mut wet = [0.0; 2];
+            let combs = self.combs.iter_mut().zip(self.comb_gains).zip(RIGHT_COMB_SIGNS);
+            for ((comb, gain), sign) in combs {
+                let sig = comb.process(input, gain, damped);
+                wet[0] += sig;
+                wet[1] += sig * sign;
+            }
+            for (wet, allpasses) in wet.iter_mut().zip(&mut self.allpasses) {
+                for allpass in allpasses {
+                    *wet = allpass.process(*wet, ALLPASS_GAIN);
+                }
+                *wet *= OUTPUT_GAIN;
+            }
+            let mix = self.mix_smoothed;
+            *out_l = dry_l + (wet[0] - dry_l) * mix;
+            *out_r = dry_r + (wet[1] - dry_r) * mix;
+        }
+        GenState::Continue
+    }
+}
+
+/// `size` sets the `decay_seconds`, from 0 at 0 to [`MAX_DECAY_SECONDS`] at 1. All the other parameters are at their defaults from [`Schroeder::param_metadata`].
+impl Reverb for Schroeder {
+    fn set_mix(&mut self, mix: Sample) {
+        self.reverb_mix = mix.clamp(0.0, 1.0);
+    }
+    fn set_size(&mut self, size: Sample) {
+        self.reverb_size = size.clamp(0.0, 1.0);
+    }
+    fn reset(&mut self) {
+        for comb in &mut self.combs {
+            comb.clear();
+        }
+        for allpass in self.allpasses.iter_mut().flatten() {
+            allpass.clear();
+        }
+        self.mix_smoothed = Sample::NAN;
+    }
+    fn process_stereo(
+        &mut self,
+        left: &[Sample],
+        right: &[Sample],
+        left_out: &mut [Sample],
+        right_out: &mut [Sample],
+        sample_rate: SampleRate,
+    ) {
+        let params = Schroeder::param_metadata();
+        self.reverb_controls.prepare(params, left.len());
+        self.reverb_controls.set(0, self.reverb_size * MAX_DECAY_SECONDS);
+        self.reverb_controls.set(2, self.reverb_mix);
+        let controls = std::mem::take(&mut self.reverb_controls);
+        self.process(
+            left,
+            right,
+            controls.get(0),
+            controls.get(1),
+            controls.get(2),
+            left_out,
+            right_out,
+            sample_rate,
+        );
+        self.reverb_controls = controls;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the left and right response of the reverb to an impulse
+    fn impulse_response(
+        reverb: &mut Schroeder,
+        decay_seconds: Sample,
+        damping: Sample,
+        mix: Sample,
+        blocks: usize,
+    ) -> [Vec<Sample>; 2] {
+        let sample_rate = SampleRate::from(48000.);
+        let block_size = 64;
+        let control = |value: Sample| vec![value; block_size];
+        let mut outputs = [Vec::new(), Vec::new()];
+        let mut left_out = vec![0.0; block_size];
+        let mut right_out = vec![0.0; block_size];
+        for block in 0..blocks {
+            let mut input = control(0.0);
+            input[0] = if block == 0 { 1.0 } else { 0.0 };
+            reverb.process(
+                &input,
+                &input,
+                &control(decay_seconds),
+                &control(damping),
+                &control(mix),
+                &mut left_out,
+                &mut right_out,
+                sample_rate,
+            );
+            outputs[0].extend_from_slice(&left_out);
+            outputs[1].extend_from_slice(&right_out);
+        }
+        outputs
+    }
+
+    fn new_schroeder() -> Schroeder {
+        let mut reverb = Schroeder::new();
+        reverb.init(SampleRate::from(48000.));
+        reverb
+    }
+
+    fn energy(samples: &[Sample]) -> Sample {
+        samples.iter().map(|s| s * s).sum()
+    }
+
+    #[test]
+    fn param_metadata_matches_inputs() {
+        use knyst::gen::Gen;
+        let reverb = Schroeder::new();
+        let inputs: Vec<_> = (0..reverb.num_inputs()).map(|i| reverb.input_desc(i)).collect();
+        let names: Vec<_> = Schroeder::param_metadata().iter().map(|p| p.name).collect();
+        assert_eq!(&inputs[2..], &names);
+        for param in Schroeder::param_metadata() {
+            assert!(param.min <= param.default && param.default <= param.max);
+        }
+        assert_eq!(reverb.num_outputs(), Schroeder::OUTPUT_CHANNELS);
+    }
+
+    #[test]
+    fn comb_gains_stay_below_one() {
+        for decay in [0.0, 0.01, 1.0, MAX_DECAY_SECONDS] {
+            for ms in COMB_LENGTHS_MS {
+                let gain = Schroeder::comb_gain(Schroeder::length(ms, 48000.0), decay, 48000.0);
+                assert!((0.0..1.0).contains(&gain));
+            }
+        }
+    }
+
+    #[test]
+    fn decay_is_the_time_to_fall_by_60_db() {
+        for decay in [0.5, 1.0, 2.0] {
+            let blocks = (decay * 48000.0 / 64.0) as usize + 1500;
+            let [left, _] = impulse_response(&mut new_schroeder(), decay, 0.0, 1.0, blocks);
+            // The energy of 0.2 seconds of the tail and the same length `decay` seconds later
+            let start = 9600;
+            let later = start + (decay * 48000.0) as usize;
+            let ratio = energy(&left[later..later + 9600]) / energy(&left[start..start + 9600]);
+            let drop = 10.0 * ratio.log10();
+            assert!((drop + 60.0).abs() < 3.0, "{decay} {drop}");
+        }
+        // Even at the longest decay the level falls
+        let [left, _] = impulse_response(&mut new_schroeder(), MAX_DECAY_SECONDS, 0.0, 1.0, 7500);
+        assert!(energy(&left[432000..480000]) < energy(&left[48000..96000]) * 0.5);
+        // Without a decay every comb echoes once, and only the allpasses ring on briefly
+        let [left, _] = impulse_response(&mut new_schroeder(), 0.0, 0.0, 1.0, 750);
+        assert!(energy(&left[9600..]) < energy(&left) * 1e-4);
+    }
+
+    #[test]
+    fn outputs_are_decorrelated() {
+        let [left, right] = impulse_response(&mut new_schroeder(), 1.5, 0.2, 1.0, 750);
+        let cross: Sample = left.iter().zip(&right).map(|(l, r)| l * r).sum();
+        let correlation = cross / (energy(&left) * energy(&right)).sqrt();
+        assert!(correlation.abs() < 0.3, "{correlation}");
+    }
+
+    #[test]
+    fn mix_of_zero_is_dry() {
+        let [left, right] = impulse_response(&mut new_schroeder(), 1.5, 0.2, 0.0, 100);
+        assert_eq!(left[0], 1.0);
+        assert_eq!(right[0], 1.0);
+        assert!(left[1..].iter().chain(&right[1..]).all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn damping_darkens_the_tail() {
+        // Energy of the first difference relative to the energy, which grows with the high frequency content
+        let brightness = |damping: Sample| {
+            let [left, _] = impulse_response(&mut new_schroeder(), 1.5, damping, 1.0, 750);
+            let tail = &left[9600..];
+            let difference: Vec<Sample> = tail.windows(2).map(|w| w[1] - w[0]).collect();
+            energy(&difference) / energy(tail)
+        };
+        let bright = brightness(0.0);
+        let dark = brightness(1.0);
+        assert!(dark < bright * 0.4);
+        assert!(brightness(0.5) < bright);
+        assert!(dark < brightness(0.5));
+    }
+
+    #[test]
+    fn reset_silences_the_reverb() {
+        let mut reverb = new_schroeder();
+        impulse_response(&mut reverb, 2.0, 0.0, 1.0, 100);
+        Reverb::reset(&mut reverb);
+        let mut left_out = vec![1.0; 64];
+        let mut right_out = vec![1.0; 64];
+        let sample_rate = SampleRate::from(48000.);
+        reverb.process_stereo(&[0.0; 64], &[0.0; 64], &mut left_out, &mut right_out, sample_rate);
+        assert!(left_out.iter().chain(&right_out).all(|&s| s == 0.0));
+    }
+}